@@ -1,79 +1,135 @@
 use eframe::egui;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::fs::File;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 use sysinfo::{System, Pid};
 
+use crate::archive;
+use crate::batch_rename;
+use crate::bookmarks::{Bookmark, BookmarkTarget, Bookmarks};
 use crate::file_associations::FileAssociations;
-use crate::indexer::FileIndexer;
-use crate::search::SearchEngine;
-use crate::tag_db::TagDatabase;
+use crate::hidden::HiddenPolicy;
+use crate::history::{History, Location};
+use crate::indexer::{FileIndexer, IndexStats};
+use crate::keymap::{Action, Context, Keymap};
+use crate::search::{SearchEngine, SearchQuery};
+use crate::logging;
+use crate::notifications::{self, NotificationCenter};
+use crate::properties::Properties;
+use crate::session::{Session, SESSION_STORAGE_KEY};
+use crate::settings::{self, Settings};
+use crate::smart_folders::{SmartFolders, SmartScope};
+use crate::tabs::{Tab, TabSet, ViewTab, TABS_STORAGE_KEY};
+use crate::tag_db::{FileEntry, FileType, TagDatabase, VisitKind};
+use crate::ui::batch_rename::{BatchRenameAction, BatchRenameDraft};
+use crate::ui::command_palette::{CommandPalette, PaletteCommand, PaletteEntry, PaletteResult};
+use crate::ui::favorites::FavoriteAction;
+use crate::ui::file_list::{format_size, FileListAction};
+use crate::ui::indexing::IndexingAction;
+use crate::ui::log_viewer::LogViewer;
+use crate::ui::notifications::NotificationAction;
+use crate::ui::settings::{SettingsAction, SettingsDraft};
+use crate::ui::smart_folders::{EditorResult, SmartFolderAction, SmartFolderDraft};
+use crate::ui::tab_bar::TabBarAction;
+use crate::ui::theme::{self, Themes};
+use crate::vfs::{self, Filesystems};
 
 pub struct FileManagerApp {
     indexer: Arc<FileIndexer>,
     search_engine: Arc<SearchEngine>,
     tag_db: Arc<TagDatabase>,
     file_associations: FileAssociations,
-    current_view: ViewTab,
-    search_query: String,
+    tabs: TabSet,
+    history: History,
+    /// The tab that was active when the history was last recorded.
+    history_tab: usize,
+    bookmarks: Bookmarks,
+    favorite_renaming: Option<(usize, String)>,
+    smart_folders: SmartFolders,
+    smart_folder_editor: Option<SmartFolderDraft>,
+    settings: Settings,
+    settings_draft: Option<SettingsDraft>,
     is_indexing: Arc<AtomicBool>,
-    folder_current_path: PathBuf,
-    tag_selected: Option<String>,
     indexing_thread: Option<std::thread::JoinHandle<()>>,
+    last_index_stats: Arc<Mutex<Option<IndexStats>>>,
+    show_indexing_panel: bool,
+    notifications: NotificationCenter,
+    show_notifications: bool,
+    log_viewer: Option<LogViewer>,
+    properties: Option<Properties>,
+    /// Entries marked with Cmd-click or Space for a batch operation; cleared
+    /// whenever the tab shows something else.
+    marked: BTreeMap<PathBuf, FileEntry>,
+    marked_location: Option<Location>,
+    batch_rename: Option<BatchRenameDraft>,
+    filesystems: Filesystems,
     last_indexed_path: PathBuf,
     search_field_id: egui::Id,
     system: System,
     last_update: Instant,
     process_id: Pid,
-    selected_file_index: Option<usize>,
-    last_search_query: String,
     directory_search_mode: bool,
     show_hidden_files: bool,
+    hidden: HiddenPolicy,
+    show_ignored_files: bool,
     expanded_directories: HashSet<PathBuf>,
     tree_root_path: PathBuf,
     show_directory_tree: bool,
+    sidebar_width: f32,
+    tag_list_width: f32,
     creating_entry: Option<CreatingEntryKind>,
     new_entry_name: String,
+    /// A trashed item waiting for the user to confirm deleting it for good.
+    confirming_delete: Option<FileEntry>,
+    keymap: Keymap,
+    themes: Themes,
+    /// The theme setting and system darkness the visuals were last built for.
+    applied_theme: Option<(String, bool)>,
+    command_palette: Option<CommandPalette>,
+    /// The selected entry of the visible list, as of the last frame.
+    selected_entry: Option<FileEntry>,
+    /// File-list actions waiting for the view that owns the list this frame.
+    list_actions: Vec<Action>,
 }
 
 impl Drop for FileManagerApp {
     fn drop(&mut self) {
         if let Some(handle) = self.indexing_thread.take() {
+            self.indexer.cancel();
             let _ = handle.join();
         }
     }
 }
 
 fn handle_list_navigation(
-    input: &egui::InputState,
+    actions: &[Action],
     selected_index: &mut Option<usize>,
     len: usize,
 ) {
-    if input.key_pressed(egui::Key::ArrowDown) {
-        *selected_index = Some(
-            selected_index
-                .map(|i| (i + 1).min(len.saturating_sub(1)))
-                .unwrap_or(0),
-        );
-    }
-
-    if input.key_pressed(egui::Key::ArrowUp) {
-        *selected_index = selected_index
-            .map(|i| i.saturating_sub(1))
-            .or_else(|| if len > 0 { Some(len - 1) } else { None });
+    for action in actions {
+        match action {
+            Action::SelectNext => {
+                *selected_index = Some(
+                    selected_index
+                        .map(|i| (i + 1).min(len.saturating_sub(1)))
+                        .unwrap_or(0),
+                );
+            }
+            Action::SelectPrevious => {
+                *selected_index = selected_index
+                    .map(|i| i.saturating_sub(1))
+                    .or_else(|| if len > 0 { Some(len - 1) } else { None });
+            }
+            Action::SelectFirst => *selected_index = (len > 0).then_some(0),
+            Action::SelectLast => *selected_index = len.checked_sub(1),
+            _ => {}
+        }
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
-enum ViewTab {
-    Folders,
-    Tags,
-}
-
 #[derive(Clone, Copy)]
 enum CreatingEntryKind {
     NewFile,
@@ -81,105 +137,102 @@ enum CreatingEntryKind {
 }
 
 impl eframe::App for FileManagerApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let input = ctx.input(|i| i.clone());
-        
-        if self.search_query != self.last_search_query {
-            self.selected_file_index = None;
-            self.last_search_query = self.search_query.clone();
-        }
-        
-        if input.key_pressed(egui::Key::F) && (input.modifiers.command || input.modifiers.ctrl) {
-            ctx.memory_mut(|m| m.request_focus(self.search_field_id));
-        }
-
-        if self.current_view == ViewTab::Folders {
-            if input.key_pressed(egui::Key::N) && (input.modifiers.command || input.modifiers.ctrl) {
-                self.creating_entry = Some(CreatingEntryKind::NewFile);
-                self.new_entry_name.clear();
-            }
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, TABS_STORAGE_KEY, &self.tabs);
+        let session = Session {
+            show_directory_tree: self.show_directory_tree,
+            show_hidden_files: self.show_hidden_files,
+            show_ignored_files: self.show_ignored_files,
+            directory_search_mode: self.directory_search_mode,
+            expanded_directories: self.expanded_directories.clone(),
+            sidebar_width: self.sidebar_width,
+            tag_list_width: self.tag_list_width,
+        };
+        eframe::set_value(storage, SESSION_STORAGE_KEY, &session);
+    }
 
-            if input.key_pressed(egui::Key::D) && (input.modifiers.command || input.modifiers.ctrl) {
-                self.creating_entry = Some(CreatingEntryKind::NewDirectory);
-                self.new_entry_name.clear();
-            }
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let system_dark = frame.info().system_theme != Some(eframe::Theme::Light);
+        self.apply_theme(ctx, system_dark);
+        self.notifications.poll();
 
-            if input.key_pressed(egui::Key::F)
-                && (input.modifiers.command || input.modifiers.ctrl)
-                && input.modifiers.alt
-            {
-                self.directory_search_mode = !self.directory_search_mode;
-                self.selected_file_index = None;
-            }
-        }
-
-        if input.key_pressed(egui::Key::Num1) && (input.modifiers.command || input.modifiers.ctrl) {
-            self.current_view = ViewTab::Folders;
-            self.selected_file_index = None;
-        }
+        let input = ctx.input(|i| i.clone());
 
-        if input.key_pressed(egui::Key::Num2) && (input.modifiers.command || input.modifiers.ctrl) {
-            self.current_view = ViewTab::Tags;
-            self.selected_file_index = None;
+        let tab = self.tabs.active_mut();
+        if tab.search_query != tab.last_search_query {
+            tab.selected_file_index = None;
+            tab.last_search_query = tab.search_query.clone();
         }
 
-        if input.key_pressed(egui::Key::Escape) {
-            if ctx.memory(|m| m.has_focus(self.search_field_id)) {
-                self.search_query.clear();
-                ctx.memory_mut(|m| m.surrender_focus(self.search_field_id));
+        // The palette handles its own keys while it is open.
+        if self.command_palette.is_none() {
+            for action in self.keymap.process(&input, ctx.wants_keyboard_input()) {
+                self.run_action(action, ctx);
             }
         }
 
-        if input.key_pressed(egui::Key::B) && (input.modifiers.command || input.modifiers.ctrl) {
-            if self.current_view == ViewTab::Folders {
-                self.show_directory_tree = !self.show_directory_tree;
-            }
+        if input.pointer.button_pressed(egui::PointerButton::Extra1) {
+            self.navigate_history(-1);
         }
-
-        let should_collapse_folders = (input.key_pressed(egui::Key::K) && (input.modifiers.command || input.modifiers.ctrl))
-            || (input.key_pressed(egui::Key::Period) && (input.modifiers.command || input.modifiers.ctrl) && input.modifiers.shift);
-        
-        if should_collapse_folders {
-            if self.current_view == ViewTab::Folders {
-                self.expanded_directories.clear();
-                let mut path_to_expand = self.folder_current_path.clone();
-                
-                while let Some(parent) = path_to_expand.parent() {
-                    let parent_path = parent.to_path_buf();
-                    if parent_path.starts_with(&self.tree_root_path) {
-                        if parent_path == self.tree_root_path {
-                            self.expanded_directories.insert(self.tree_root_path.clone());
-                            break;
-                        } else {
-                            self.expanded_directories.insert(parent_path.clone());
-                        }
-                    }
-                    path_to_expand = parent_path;
-                    if path_to_expand == self.tree_root_path {
-                        break;
-                    }
-                }
-            }
+        if input.pointer.button_pressed(egui::PointerButton::Extra2) {
+            self.navigate_history(1);
         }
 
-        if input.key_pressed(egui::Key::Period) && (input.modifiers.command || input.modifiers.ctrl) && !input.modifiers.shift {
-            self.show_hidden_files = !self.show_hidden_files;
-            self.selected_file_index = None;
+        if input.key_pressed(egui::Key::Escape) && ctx.memory(|m| m.has_focus(self.search_field_id)) {
+            self.tabs.active_mut().search_query.clear();
+            ctx.memory_mut(|m| m.surrender_focus(self.search_field_id));
         }
 
+        let mut history_offset = None;
+        let mut add_favorite = false;
+        let mut save_search = false;
+        let mut open_settings = false;
         egui::TopBottomPanel::top("top_panel")
             .show(ctx, |ui| {
+                match crate::ui::tab_bar::render_tab_bar(ui, &self.tabs) {
+                    Some(TabBarAction::Select(index)) => self.tabs.select(index),
+                    Some(TabBarAction::Close(index)) => {
+                        self.tabs.close(index);
+                    }
+                    Some(TabBarAction::New) => self.open_tab(),
+                    None => {}
+                }
+                ui.separator();
+
+                let directory_search_mode = self.directory_search_mode;
+                let history = &self.history;
+                let show_ignored_files = &mut self.show_ignored_files;
+                let tab = self.tabs.active_mut();
                 ui.horizontal(|ui| {
-                    ui.selectable_value(&mut self.current_view, ViewTab::Folders, "Folders");
-                    ui.selectable_value(&mut self.current_view, ViewTab::Tags, "Tags");
+                    history_offset = crate::ui::navigation::render_navigation_buttons(ui, history);
+                    if ui.button("☆").on_hover_text("Add to favorites").clicked() {
+                        add_favorite = true;
+                    }
+                    if ui
+                        .add_enabled(!tab.search_query.is_empty(), egui::Button::new("💾"))
+                        .on_hover_text("Save search as smart folder")
+                        .clicked()
+                    {
+                        save_search = true;
+                    }
+                    ui.separator();
+                    ui.selectable_value(&mut tab.view, ViewTab::Folders, "Folders");
+                    ui.selectable_value(&mut tab.view, ViewTab::Tags, "Tags");
+                    ui.selectable_value(&mut tab.view, ViewTab::Recent, "Recent");
+                    ui.selectable_value(&mut tab.view, ViewTab::Smart, "Smart");
                     
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        let hint = if self.current_view == ViewTab::Folders && self.directory_search_mode {
+                        if ui.button("⚙").on_hover_text("Settings (Ctrl+,)").clicked() {
+                            open_settings = true;
+                        }
+                        ui.toggle_value(show_ignored_files, "Ignored")
+                            .on_hover_text("Show entries matched by .gitignore, .ignore or .fmsignore");
+                        let hint = if tab.view == ViewTab::Folders && directory_search_mode {
                             "Search in directory..."
                         } else {
                             "Search files..."
                         };
-                        ui.add(egui::TextEdit::singleline(&mut self.search_query)
+                        ui.add(egui::TextEdit::singleline(&mut tab.search_query)
                             .id(self.search_field_id)
                             .hint_text(hint)
                             .desired_width(300.0));
@@ -187,30 +240,57 @@ impl eframe::App for FileManagerApp {
                 });
             });
 
-        if self.current_view == ViewTab::Folders && self.folder_current_path != self.last_indexed_path {
-            let path_to_index = self.folder_current_path.clone();
-            let indexer = self.indexer.clone();
-            std::thread::spawn(move || {
-                if let Err(e) = indexer.index_directory_shallow(&path_to_index) {
-                    eprintln!("Error indexing directory: {}", e);
+        if let Some(offset) = history_offset {
+            self.navigate_history(offset);
+        }
+
+        if add_favorite {
+            self.add_current_to_favorites();
+        }
+
+        if save_search {
+            self.start_saving_search();
+        }
+
+        if open_settings {
+            self.open_settings();
+        }
+
+        if self.tabs.active().view == ViewTab::Folders && self.tabs.active().folder_current_path != self.last_indexed_path {
+            let path_to_index = self.tabs.active().folder_current_path.clone();
+            if !self.last_indexed_path.as_os_str().is_empty() {
+                if let Err(e) = self.tag_db.record_visit(&path_to_index, VisitKind::Entered) {
+                    notifications::warning!(format!("Could not record visit: {}", e), Some(&path_to_index));
                 }
-            });
-            self.last_indexed_path = self.folder_current_path.clone();
+            }
+            self.refresh_current_directory();
+            self.last_indexed_path = self.tabs.active().folder_current_path.clone();
             
-            let mut path_to_expand = self.folder_current_path.clone();
+            let mut path_to_expand = self.last_indexed_path.clone();
             while let Some(parent) = path_to_expand.parent() {
                 self.expanded_directories.insert(parent.to_path_buf());
                 path_to_expand = parent.to_path_buf();
             }
         }
 
-        if self.current_view == ViewTab::Folders && self.show_directory_tree {
+        if self.tabs.active().view != ViewTab::Tags && self.show_directory_tree {
             let tree_root = self.tree_root_path.clone();
-            let current_path = self.folder_current_path.clone();
+            let current_path = self.tabs.active().folder_current_path.clone();
             let tag_db = self.tag_db.clone();
             let mut expanded_dirs = std::mem::take(&mut self.expanded_directories);
             let show_hidden = self.show_hidden_files;
+            let hidden = &self.hidden;
+            let show_ignored = self.show_ignored_files;
             let mut path_to_set: Option<PathBuf> = None;
+            let bookmarks = &self.bookmarks;
+            let favorite_renaming = &mut self.favorite_renaming;
+            let mut favorite_action = None;
+            let smart_folders = &self.smart_folders;
+            let selected_smart_folder = match self.tabs.active().view {
+                ViewTab::Smart => self.tabs.active().smart_folder.clone(),
+                _ => None,
+            };
+            let mut smart_folder_action = None;
             
             let mut path_to_expand = current_path.clone();
             while let Some(parent) = path_to_expand.parent() {
@@ -224,36 +304,56 @@ impl eframe::App for FileManagerApp {
                 path_to_expand = parent_path;
             }
             
-            egui::SidePanel::left("folder_tree")
-                .resizable(false)
-                .default_width(150.0)
-                .min_width(150.0)
-                .max_width(150.0)
+            let sidebar = egui::SidePanel::left("folder_tree")
+                .resizable(true)
+                .default_width(self.sidebar_width)
+                .min_width(120.0)
+                .max_width(480.0)
                 .show(ctx, |ui| {
                     ui.vertical(|ui| {
+                        favorite_action = crate::ui::favorites::render_favorites(
+                            ui,
+                            bookmarks,
+                            favorite_renaming,
+                        );
+                        ui.add_space(8.0);
+
+                        smart_folder_action = crate::ui::smart_folders::render_smart_folders(
+                            ui,
+                            smart_folders,
+                            selected_smart_folder.as_deref(),
+                        );
+                        ui.add_space(8.0);
+
                         ui.heading("Directory tree");
                         ui.separator();
                         
+                        let tree_width = ui.available_width();
                         let height = ui.available_size().y;
-                        let available_size = egui::vec2(150.0, height);
+                        let available_size = egui::vec2(tree_width, height);
                         ui.allocate_ui(available_size, |ui| {
                             egui::ScrollArea::vertical()
                                 .auto_shrink([false, false])
                                 .show(ui, |ui| {
                                     ui.allocate_ui(
-                                        egui::vec2(150.0, ui.available_height()),
+                                        egui::vec2(tree_width, ui.available_height()),
                                         |ui| {
+                                            let tree = crate::ui::file_tree::TreeContext {
+                                                tag_db: &tag_db,
+                                                root_path: &tree_root,
+                                                current_path: &current_path,
+                                                show_hidden_files: show_hidden,
+                                                hidden,
+                                                show_ignored_files: show_ignored,
+                                                max_width: tree_width,
+                                            };
                                             crate::ui::file_tree::render_file_tree(
                                                 ui,
-                                                &tag_db,
-                                                &tree_root,
-                                                &current_path,
+                                                &tree,
                                                 &mut expanded_dirs,
-                                                show_hidden,
                                                 &mut |path| {
                                                     path_to_set = Some(path.clone());
                                                 },
-                                                150.0,
                                             );
                                         }
                                     );
@@ -261,10 +361,13 @@ impl eframe::App for FileManagerApp {
                         });
                     });
                 });
+            self.sidebar_width = sidebar.response.rect.width();
             
             if let Some(path) = path_to_set {
-                self.folder_current_path = path.clone();
-                self.selected_file_index = None;
+                let tab = self.tabs.active_mut();
+                tab.view = ViewTab::Folders;
+                tab.folder_current_path = path.clone();
+                tab.selected_file_index = None;
                 
                 let mut path_to_expand = path;
                 while let Some(parent) = path_to_expand.parent() {
@@ -276,41 +379,59 @@ impl eframe::App for FileManagerApp {
                 }
             }
             self.expanded_directories = expanded_dirs;
+
+            if let Some(action) = favorite_action {
+                self.handle_favorite_action(action);
+            }
+
+            if let Some(action) = smart_folder_action {
+                self.handle_smart_folder_action(action);
+            }
         }
 
+        let list_actions = std::mem::take(&mut self.list_actions);
+        let mut list_action = None;
         egui::CentralPanel::default().show(ctx, |ui| {
-            match self.current_view {
+            let tab = self.tabs.active_mut();
+            match tab.view {
                 ViewTab::Folders => {
-                    let current_path = self.folder_current_path.clone();
-                    let files_result = if self.search_query.is_empty() {
+                    let current_path = tab.folder_current_path.clone();
+                    let location = vfs::Location::of_path(&current_path);
+                    let files_result = if tab.search_query.is_empty() && !matches!(location, vfs::Location::Local(_)) {
+                        Ok(self.filesystems.list(&location).unwrap_or_default())
+                    } else if tab.search_query.is_empty() {
                         self.tag_db.get_files_in_directory(&current_path)
                     } else if self.directory_search_mode {
-                        self.search_engine.search_in_directory(&current_path, &self.search_query)
+                        self.search_engine.search_in_directory(&current_path, &tab.search_query)
                     } else {
-                        self.search_engine.search(&self.search_query)
+                        self.search_engine.search(&tab.search_query)
                     };
                     let mut files = files_result.unwrap_or_default();
                     
                     if !self.show_hidden_files {
-                        files.retain(|file| !file.name.starts_with('.'));
+                        self.hidden.retain_visible(&mut files);
+                    }
+
+                    if !self.show_ignored_files {
+                        files.retain(|file| !file.ignored);
                     }
+
+                    tab.sort.apply(&mut files);
                     
-                    let input = ctx.input(|i| i.clone());
                     let files_len = files.len();
 
                     if files_len > 0 {
-                        handle_list_navigation(&input, &mut self.selected_file_index, files_len);
+                        handle_list_navigation(&list_actions, &mut tab.selected_file_index, files_len);
                     } else {
-                        self.selected_file_index = None;
+                        tab.selected_file_index = None;
                     }
                     
-                    if input.key_pressed(egui::Key::Enter) {
-                        if let Some(idx) = self.selected_file_index {
+                    if list_actions.contains(&Action::OpenSelected) {
+                        if let Some(idx) = tab.selected_file_index {
                             if let Some(file) = files.get(idx) {
-                                let is_dir = matches!(file.file_type, crate::tag_db::FileType::Directory);
-                                if is_dir {
-                                    self.folder_current_path = file.path.clone();
-                                    self.selected_file_index = None;
+                                if file.is_dir() || archive::is_archive(file) {
+                                    tab.folder_current_path = file.path.clone();
+                                    tab.selected_file_index = None;
                                 } else {
                                     let _ = self.file_associations.open_file(&file.path);
                                 }
@@ -318,33 +439,37 @@ impl eframe::App for FileManagerApp {
                         }
                     }
                     
-                    if (input.key_pressed(egui::Key::ArrowLeft) || input.key_pressed(egui::Key::Backspace)) 
-                        && !ctx.memory(|m| m.has_focus(self.search_field_id)) {
-                        if let Some(parent) = self.folder_current_path.parent() {
-                            self.folder_current_path = parent.to_path_buf();
-                            self.selected_file_index = None;
+                    if list_actions.contains(&Action::GoToParent) {
+                        if let Some(parent) = tab.folder_current_path.parent() {
+                            tab.folder_current_path = parent.to_path_buf();
+                            tab.selected_file_index = None;
                         }
                     }
                     
-                    let selected_index = self.selected_file_index;
-                    let current_path = self.folder_current_path.clone();
-                    let search_query_empty = self.search_query.is_empty();
-                    let tree_root = self.tree_root_path.clone();
-                    let tag_db = self.tag_db.clone();
+                    self.selected_entry = tab.selected_file_index.and_then(|idx| files.get(idx)).cloned();
+                    let selected_index = tab.selected_file_index;
+                    let current_path = tab.folder_current_path.clone();
+                    let mut scroll = tab.scroll;
+                    let mut sort = tab.sort;
                     let mut expanded_dirs = std::mem::take(&mut self.expanded_directories);
                     let mut path_to_expand_after: Option<PathBuf> = None;
-                    crate::ui::folder_view::render_folder_view(
+                    list_action = crate::ui::folder_view::render_folder_view(
                         files,
                         current_path,
                         &mut |path| {
-                            self.folder_current_path = path.clone();
-                            self.selected_file_index = None;
+                            tab.folder_current_path = path.clone();
+                            tab.selected_file_index = None;
                             path_to_expand_after = Some(path);
                         },
                         selected_index,
+                        &self.marked,
+                        &mut scroll,
+                        &mut sort,
                         &self.file_associations,
                         ui,
                     );
+                    tab.scroll = scroll;
+                    tab.sort = sort;
                     if let Some(path) = path_to_expand_after {
                         let mut path_to_expand = path.clone();
                         while let Some(parent) = path_to_expand.parent() {
@@ -355,53 +480,273 @@ impl eframe::App for FileManagerApp {
                     self.expanded_directories = expanded_dirs;
                 }
                 ViewTab::Tags => {
-                    let files_result = if let Some(tag) = &self.tag_selected {
-                        self.search_engine.search_by_tag(tag, &self.search_query)
-                    } else if self.search_query.is_empty() {
+                    let files_result = if let (Some(tag), true) = (&tab.tag_selected, tab.search_query.is_empty()) {
+                        Ok(self.filesystems.list(&vfs::Location::Tag(tag.clone())).unwrap_or_default())
+                    } else if let Some(tag) = &tab.tag_selected {
+                        self.search_engine.search_by_tag(tag, &tab.search_query)
+                    } else if let Some(kind) = tab.kind_selected {
+                        self.search_engine.search_by_kind(kind, &tab.search_query)
+                    } else if tab.search_query.is_empty() {
                         Ok(vec![])
                     } else {
-                        self.search_engine.search(&self.search_query)
+                        self.search_engine.search(&tab.search_query)
                     };
                     let mut files = files_result.unwrap_or_default();
                     
                     if !self.show_hidden_files {
-                        files.retain(|file| !file.name.starts_with('.'));
+                        self.hidden.retain_visible(&mut files);
+                    }
+
+                    if !self.show_ignored_files {
+                        files.retain(|file| !file.ignored);
                     }
+
+                    tab.sort.apply(&mut files);
                     
-                    let input = ctx.input(|i| i.clone());
                     let files_len = files.len();
 
                     if files_len > 0 {
-                        handle_list_navigation(&input, &mut self.selected_file_index, files_len);
+                        handle_list_navigation(&list_actions, &mut tab.selected_file_index, files_len);
                     } else {
-                        self.selected_file_index = None;
+                        tab.selected_file_index = None;
                     }
                     
-                    if input.key_pressed(egui::Key::Enter) {
-                        if let Some(idx) = self.selected_file_index {
+                    if list_actions.contains(&Action::OpenSelected) {
+                        if let Some(idx) = tab.selected_file_index {
                             if let Some(file) = files.get(idx) {
                                 let _ = self.file_associations.open_file(&file.path);
                             }
                         }
                     }
                     
-                    let selected_index = self.selected_file_index;
-                    crate::ui::tag_view::render_tag_view(
+                    self.selected_entry = tab.selected_file_index.and_then(|idx| files.get(idx)).cloned();
+                    let selected_index = tab.selected_file_index;
+                    let mut scroll = tab.scroll;
+                    let mut tag_choice = None;
+                    let mut kind_choice = None;
+                    list_action = crate::ui::tag_view::render_tag_view(
                         self.tag_db.clone(),
                         files,
-                        self.tag_selected.clone(),
-                        &mut |tag| {
-                            self.tag_selected = tag;
-                            self.selected_file_index = None;
-                        },
+                        tab.tag_selected.clone(),
+                        &mut |tag| tag_choice = Some(tag),
+                        tab.kind_selected,
+                        &mut |kind| kind_choice = Some(kind),
                         selected_index,
+                        &self.marked,
+                        &mut scroll,
                         &self.file_associations,
+                        &mut self.tag_list_width,
                         ui,
                     );
+                    tab.scroll = scroll;
+                    // A tag and a kind are alternative groups; picking one clears the other.
+                    if let Some(tag) = tag_choice {
+                        tab.tag_selected = tag;
+                        tab.kind_selected = None;
+                        tab.selected_file_index = None;
+                    } else if let Some(kind) = kind_choice {
+                        tab.kind_selected = Some(kind);
+                        tab.tag_selected = None;
+                        tab.selected_file_index = None;
+                    }
+                }
+                ViewTab::Recent => {
+                    let mut files = self.search_engine.search_recent(&tab.search_query).unwrap_or_default();
+
+                    if !self.show_hidden_files {
+                        self.hidden.retain_visible(&mut files);
+                    }
+
+                    if !self.show_ignored_files {
+                        files.retain(|file| !file.ignored);
+                    }
+
+                    let files_len = files.len();
+
+                    if files_len > 0 {
+                        handle_list_navigation(&list_actions, &mut tab.selected_file_index, files_len);
+                    } else {
+                        tab.selected_file_index = None;
+                    }
+
+                    let open_dir = |tab: &mut crate::tabs::Tab, path: PathBuf| {
+                        tab.view = ViewTab::Folders;
+                        tab.folder_current_path = path;
+                        tab.search_query.clear();
+                        tab.selected_file_index = None;
+                    };
+
+                    if list_actions.contains(&Action::OpenSelected) {
+                        if let Some(file) = tab.selected_file_index.and_then(|idx| files.get(idx)) {
+                            if file.is_dir() {
+                                let path = file.path.clone();
+                                open_dir(tab, path);
+                            } else {
+                                let _ = self.file_associations.open_file(&file.path);
+                            }
+                        }
+                    }
+
+                    self.selected_entry = tab.selected_file_index.and_then(|idx| files.get(idx)).cloned();
+                    let selected_index = tab.selected_file_index;
+                    let mut scroll = tab.scroll;
+                    let mut dir_to_open = None;
+                    list_action = crate::ui::recent_view::render_recent_view(
+                        files,
+                        &mut |path| dir_to_open = Some(path),
+                        selected_index,
+                        &self.marked,
+                        &mut scroll,
+                        &self.file_associations,
+                        ui,
+                    );
+                    tab.scroll = scroll;
+                    if let Some(path) = dir_to_open {
+                        open_dir(tab, path);
+                    }
+                }
+                ViewTab::Smart => {
+                    let folder = tab.smart_folder.as_deref().and_then(|name| self.smart_folders.get(name));
+                    let mut files = folder
+                        .map(|folder| folder.evaluate(&self.search_engine).unwrap_or_default())
+                        .unwrap_or_default();
+
+                    if !tab.search_query.is_empty() {
+                        let query = SearchQuery::parse(&tab.search_query);
+                        files.retain(|file| query.matches(file));
+                    }
+
+                    if !self.show_hidden_files {
+                        self.hidden.retain_visible(&mut files);
+                    }
+
+                    if !self.show_ignored_files {
+                        files.retain(|file| !file.ignored);
+                    }
+
+                    let files_len = files.len();
+
+                    if files_len > 0 {
+                        handle_list_navigation(&list_actions, &mut tab.selected_file_index, files_len);
+                    } else {
+                        tab.selected_file_index = None;
+                    }
+
+                    let mut dir_to_open = None;
+                    if list_actions.contains(&Action::OpenSelected) {
+                        if let Some(file) = tab.selected_file_index.and_then(|idx| files.get(idx)) {
+                            if file.is_dir() {
+                                dir_to_open = Some(file.path.clone());
+                            } else {
+                                let _ = self.file_associations.open_file(&file.path);
+                            }
+                        }
+                    }
+
+                    self.selected_entry = tab.selected_file_index.and_then(|idx| files.get(idx)).cloned();
+                    let selected_index = tab.selected_file_index;
+                    let mut scroll = tab.scroll;
+                    list_action = crate::ui::smart_view::render_smart_view(
+                        folder,
+                        files,
+                        &mut |path| dir_to_open = Some(path),
+                        selected_index,
+                        &self.marked,
+                        &mut scroll,
+                        &self.file_associations,
+                        ui,
+                    );
+                    tab.scroll = scroll;
+                    if let Some(path) = dir_to_open {
+                        tab.view = ViewTab::Folders;
+                        tab.folder_current_path = path;
+                        tab.search_query.clear();
+                        tab.selected_file_index = None;
+                    }
                 }
             }
         });
 
+        match list_action {
+            Some(FileListAction::ShowProperties(file)) => self.show_properties(file),
+            Some(FileListAction::ToggleMark(file)) => self.toggle_mark(file),
+            Some(FileListAction::Rename(file)) => self.open_batch_rename(Some(file)),
+            Some(FileListAction::Extract(file)) => self.extract_from_archive(&file),
+            Some(FileListAction::DeletePermanently(file)) => self.confirming_delete = Some(file),
+            None => {}
+        }
+
+        if let Some(draft) = &mut self.batch_rename {
+            let filesystems = &self.filesystems;
+            let exists = |path: &Path| filesystems.for_path(path).stat(path).is_ok();
+            match crate::ui::batch_rename::render_batch_rename_window(ctx, draft, &exists) {
+                Some(BatchRenameAction::Apply(plan)) => self.apply_batch_rename(&plan),
+                Some(BatchRenameAction::Cancel) => self.batch_rename = None,
+                None => {}
+            }
+        }
+
+        if let Some(properties) = &mut self.properties {
+            if !crate::ui::properties::render_properties_window(ctx, properties) {
+                self.properties = None;
+            }
+        }
+
+        if let Some(draft) = &mut self.settings_draft {
+            match crate::ui::settings::render_settings_window(
+                ctx,
+                draft,
+                self.is_indexing.load(Ordering::Relaxed),
+                &self.keymap,
+                &self.themes,
+            ) {
+                Some(SettingsAction::Save { reindex }) => {
+                    self.settings.index = draft.to_index_settings();
+                    self.settings.hidden = draft.to_hidden_settings();
+                    self.settings.appearance = draft.to_appearance_settings();
+                    self.settings.save();
+                    self.hidden = HiddenPolicy::new(&self.settings.hidden);
+                    self.keymap.set_preset(draft.keymap_preset);
+                    self.indexer.set_roots(self.settings.index.roots.clone());
+                    self.settings_draft = None;
+                    if reindex {
+                        self.start_indexing();
+                    }
+                }
+                Some(SettingsAction::Cancel) => self.settings_draft = None,
+                None => {}
+            }
+        }
+
+        if let Some(draft) = &mut self.smart_folder_editor {
+            match crate::ui::smart_folders::render_smart_folder_editor(ctx, draft) {
+                Some(EditorResult::Save(folder)) => {
+                    let previous_name = draft.previous_name.clone();
+                    let name = folder.name.clone();
+                    self.smart_folders.upsert(previous_name.as_deref(), folder);
+                    self.smart_folder_editor = None;
+                    self.open_smart_folder(&name);
+                }
+                Some(EditorResult::Cancel) => self.smart_folder_editor = None,
+                None => {}
+            }
+        }
+
+        if self.command_palette.is_some() {
+            let entries = self.palette_entries();
+            let result = self.command_palette.as_mut().and_then(|palette| {
+                crate::ui::command_palette::render_command_palette(ctx, palette, &entries)
+            });
+            match result {
+                Some(PaletteResult::Execute(command)) => {
+                    self.command_palette = None;
+                    self.execute_palette_command(command, ctx);
+                }
+                Some(PaletteResult::Close) => self.command_palette = None,
+                None => {}
+            }
+        }
+
         if let Some(kind) = self.creating_entry {
             let mut create_now = false;
             let mut cancel = false;
@@ -429,10 +774,9 @@ impl eframe::App for FileManagerApp {
                         if ui
                             .add_enabled(can_create, egui::Button::new("Create"))
                             .clicked()
+                            && can_create
                         {
-                            if can_create {
-                                create_now = true;
-                            }
+                            create_now = true;
                         }
                         if ui.button("Cancel").clicked() {
                             cancel = true;
@@ -469,6 +813,40 @@ impl eframe::App for FileManagerApp {
             }
         }
 
+        if let Some(file) = &self.confirming_delete {
+            let mut delete_now = false;
+            let mut cancel = input.key_pressed(egui::Key::Escape);
+
+            egui::Window::new("Delete permanently?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(egui::RichText::new(&file.name).strong());
+                    ui.label(match file.file_type {
+                        FileType::Directory => "A folder, deleted with everything in it".to_string(),
+                        _ => format!("{} ({} bytes)", format_size(file.size), file.size),
+                    });
+                    ui.label("This cannot be undone.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Delete").clicked() {
+                            delete_now = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
+                        }
+                    });
+                });
+
+            if delete_now {
+                if let Some(file) = self.confirming_delete.take() {
+                    self.delete_permanently(&file);
+                }
+            } else if cancel {
+                self.confirming_delete = None;
+            }
+        }
+
         if self.last_update.elapsed() > Duration::from_millis(500) {
             self.system.refresh_process(self.process_id);
             self.system.refresh_memory();
@@ -486,115 +864,701 @@ impl eframe::App for FileManagerApp {
             0.0
         };
 
+        let is_indexing = self.is_indexing.load(Ordering::Relaxed);
+        let progress = self.indexer.progress();
+        let last_index_stats = *self.last_index_stats.lock().unwrap();
+        if is_indexing {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+
+        let mut indexing_action = None;
+        let mut notification_action = None;
+        let notification_center = &self.notifications;
         egui::TopBottomPanel::bottom("status_bar")
             .show(ctx, |ui| {
                 ui.horizontal(|ui| {
-                    if self.is_indexing.load(Ordering::Relaxed) {
-                        ui.label("Indexing files...");
-                    } else {
-                        ui.label("Ready");
-                    }
+                    notification_action = crate::ui::notifications::render_notification_status(ui, notification_center);
+                    ui.separator();
+                    indexing_action = crate::ui::indexing::render_indexing_status(
+                        ui,
+                        is_indexing,
+                        &progress,
+                        last_index_stats.as_ref(),
+                    );
                     ui.separator();
                     ui.label(format!("Memory: {:.1} MB", memory_mb));
                     ui.separator();
                     ui.label(format!("CPU: {:.1}%", cpu_usage));
                 });
             });
+
+        if self.show_indexing_panel {
+            if let Some(action) = crate::ui::indexing::render_indexing_panel(
+                ctx,
+                is_indexing,
+                &progress,
+                last_index_stats.as_ref(),
+            ) {
+                indexing_action = Some(action);
+            }
+        }
+        if let Some(action) = indexing_action {
+            self.handle_indexing_action(action);
+        }
+
+        if let Some(action) = crate::ui::notifications::render_toasts(ctx, &self.notifications) {
+            notification_action = Some(action);
+        }
+        if self.show_notifications {
+            if let Some(action) = crate::ui::notifications::render_notifications_panel(ctx, &self.notifications) {
+                notification_action = Some(action);
+            }
+            self.notifications.mark_read();
+        }
+        if let Some(action) = notification_action {
+            self.handle_notification_action(action);
+        }
+
+        if let Some(viewer) = &mut self.log_viewer {
+            if !crate::ui::log_viewer::render_log_viewer(ctx, viewer) {
+                self.log_viewer = None;
+            }
+        }
+
+        let location = Location::of(self.tabs.active());
+        if self.marked_location.as_ref() != Some(&location) {
+            self.marked.clear();
+            self.marked_location = Some(location.clone());
+        }
+        if self.tabs.active_index() == self.history_tab {
+            self.history.record(location);
+        } else {
+            self.history_tab = self.tabs.active_index();
+            self.history.replace_current(location);
+        }
     }
 }
 
 impl FileManagerApp {
-    pub fn new() -> Self {
+    /// `smart_folder` names a smart folder to open in a new tab on startup.
+    pub fn new(cc: &eframe::CreationContext<'_>, smart_folder: Option<String>) -> Self {
         let home_dir = std::env::var("HOME")
             .map(PathBuf::from)
             .unwrap_or_else(|_| PathBuf::from("/"));
 
-        let tag_db = Arc::new(TagDatabase::new().expect("Failed to create tag database"));
+        // Installed first so problems loading the config files are shown.
+        let notifications = NotificationCenter::install();
+        let mut file_associations = FileAssociations::new();
+        let config_dir = settings::config_dir();
+        let usage_path = config_dir.join("usage.db");
+        let tag_db = match TagDatabase::with_usage_store(&usage_path) {
+            Ok(tag_db) => tag_db,
+            Err(e) => {
+                notifications::error!(format!("Could not open usage store: {}", e), Some(&usage_path));
+                TagDatabase::new().expect("Failed to create tag database")
+            }
+        };
+        let tag_db = Arc::new(tag_db);
         let indexer = Arc::new(FileIndexer::new(tag_db.clone()));
         let search_engine = Arc::new(SearchEngine::new(tag_db.clone()));
-        let file_associations = FileAssociations::new();
-        let is_indexing = Arc::new(AtomicBool::new(true));
+        let filesystems = Filesystems::new(tag_db.clone());
+        file_associations.set_visit_log(tag_db.clone());
+        let bookmarks = Bookmarks::new(&config_dir);
+        let smart_folders = SmartFolders::new(&config_dir);
+        let settings = Settings::load(&config_dir);
+        logging::configure(&settings.logging);
+        let hidden = HiddenPolicy::new(&settings.hidden);
+        let keymap = Keymap::new(&config_dir);
+        let themes = Themes::load(&config_dir);
+        let is_indexing = Arc::new(AtomicBool::new(false));
 
-        let is_indexing_clone = is_indexing.clone();
-        let indexer_clone = indexer.clone();
-        let home_dir_clone = home_dir.clone();
-        let root_path = PathBuf::from("/");
+        let session = cc
+            .storage
+            .and_then(|s| eframe::get_value::<Session>(s, SESSION_STORAGE_KEY))
+            .unwrap_or_default();
 
-        let indexing_thread = Some(std::thread::spawn(move || {
-            if let Err(e) = indexer_clone.index_file(&root_path) {
-                eprintln!("Error indexing root directory entry: {}", e);
-            }
-            if let Err(e) = indexer_clone.index_directory_shallow(&root_path) {
-                eprintln!("Error indexing root directory: {}", e);
-            }
-            if let Err(e) = indexer_clone.index_directory_with_depth(&home_dir_clone, 3) {
-                eprintln!("Error indexing directory: {}", e);
+        let mut tabs = match cc.storage.and_then(|s| eframe::get_value::<TabSet>(s, TABS_STORAGE_KEY)) {
+            Some(tabs) => tabs.restored(Tab::new(home_dir.clone())),
+            None => TabSet::new(Tab::new(home_dir.clone())),
+        };
+
+        if let Some(name) = smart_folder {
+            if smart_folders.get(&name).is_some() {
+                let mut tab = Tab::new(home_dir.clone());
+                tab.view = ViewTab::Smart;
+                tab.smart_folder = Some(name);
+                tabs.open(tab);
+            } else {
+                notifications::error!(format!("No smart folder named {:?}", name), None);
             }
-            is_indexing_clone.store(false, Ordering::Relaxed);
-        }));
+        }
 
         let mut system = System::new();
         let process_id = Pid::from_u32(std::process::id());
         system.refresh_process(process_id);
         system.refresh_memory();
 
-        FileManagerApp {
+        let mut app = FileManagerApp {
             indexer,
             search_engine,
             tag_db,
             file_associations,
-            current_view: ViewTab::Folders,
-            search_query: String::new(),
+            tabs,
+            history: History::new(),
+            history_tab: 0,
+            bookmarks,
+            favorite_renaming: None,
+            smart_folders,
+            smart_folder_editor: None,
+            settings,
+            settings_draft: None,
             is_indexing,
-            folder_current_path: home_dir.clone(),
-            tag_selected: None,
-            indexing_thread,
+            indexing_thread: None,
+            last_index_stats: Arc::new(Mutex::new(None)),
+            show_indexing_panel: false,
+            notifications,
+            show_notifications: false,
+            log_viewer: None,
+            properties: None,
+            marked: BTreeMap::new(),
+            marked_location: None,
+            batch_rename: None,
+            filesystems,
             last_indexed_path: PathBuf::new(),
             search_field_id: egui::Id::new("search_field"),
             system,
             last_update: Instant::now(),
             process_id,
-            selected_file_index: None,
-            last_search_query: String::new(),
-            directory_search_mode: false,
-            show_hidden_files: false,
-            expanded_directories: HashSet::new(),
+            directory_search_mode: session.directory_search_mode,
+            show_hidden_files: session.show_hidden_files,
+            hidden,
+            show_ignored_files: session.show_ignored_files,
+            expanded_directories: session.expanded_directories,
             tree_root_path: PathBuf::from("/"),
-            show_directory_tree: true,
+            show_directory_tree: session.show_directory_tree,
+            sidebar_width: session.sidebar_width,
+            tag_list_width: session.tag_list_width,
             creating_entry: None,
             new_entry_name: String::new(),
+            confirming_delete: None,
+            keymap,
+            themes,
+            applied_theme: None,
+            command_palette: None,
+            selected_entry: None,
+            list_actions: Vec::new(),
+        };
+        app.start_indexing();
+        app
+    }
+
+    fn run_action(&mut self, action: Action, ctx: &egui::Context) {
+        let view = self.tabs.active().view;
+        if action.context() == Context::Folders && view != ViewTab::Folders {
+            return;
+        }
+
+        match action {
+            Action::NewTab => self.open_tab(),
+            Action::CloseTab => {
+                let active = self.tabs.active_index();
+                self.tabs.close(active);
+            }
+            Action::NextTab => self.tabs.select_next(),
+            Action::PreviousTab => self.tabs.select_previous(),
+            Action::Back => self.navigate_history(-1),
+            Action::Forward => self.navigate_history(1),
+            Action::FocusSearch => ctx.memory_mut(|m| m.request_focus(self.search_field_id)),
+            Action::ShowFolders | Action::ShowTags | Action::ShowRecent | Action::ShowSmart => {
+                let tab = self.tabs.active_mut();
+                tab.view = match action {
+                    Action::ShowFolders => ViewTab::Folders,
+                    Action::ShowTags => ViewTab::Tags,
+                    Action::ShowRecent => ViewTab::Recent,
+                    _ => ViewTab::Smart,
+                };
+                tab.selected_file_index = None;
+            }
+            Action::ToggleHidden => {
+                self.show_hidden_files = !self.show_hidden_files;
+                self.tabs.active_mut().selected_file_index = None;
+            }
+            Action::OpenSettings => self.open_settings(),
+            Action::ToggleTree => {
+                if view != ViewTab::Tags {
+                    self.show_directory_tree = !self.show_directory_tree;
+                }
+            }
+            Action::CollapseTree => self.collapse_tree_to_current(),
+            Action::NewFile => {
+                self.creating_entry = Some(CreatingEntryKind::NewFile);
+                self.new_entry_name.clear();
+            }
+            Action::NewFolder => {
+                self.creating_entry = Some(CreatingEntryKind::NewDirectory);
+                self.new_entry_name.clear();
+            }
+            Action::ToggleDirectorySearch => {
+                self.directory_search_mode = !self.directory_search_mode;
+                self.tabs.active_mut().selected_file_index = None;
+            }
+            Action::OpenFavorite(index) => self.open_bookmark(index),
+            Action::SelectNext
+            | Action::SelectPrevious
+            | Action::SelectFirst
+            | Action::SelectLast
+            | Action::OpenSelected
+            | Action::GoToParent => self.list_actions.push(action),
+            Action::CommandPalette => self.command_palette = Some(CommandPalette::default()),
+            Action::ShowLog => self.show_log(),
+            Action::ToggleMark => {
+                if let Some(file) = self.selected_entry.clone() {
+                    self.toggle_mark(file);
+                }
+            }
+            Action::BatchRename => {
+                if self.batch_rename.is_none() {
+                    self.open_batch_rename(self.selected_entry.clone());
+                }
+            }
+            Action::ShowProperties => {
+                if let Some(file) = self.selected_entry.clone() {
+                    self.show_properties(file);
+                }
+            }
+        }
+    }
+
+    /// Everything the command palette offers right now: the actions that
+    /// apply in the current view plus favorites, tags and "Open with" apps
+    /// for the selected file.
+    fn palette_entries(&self) -> Vec<PaletteEntry> {
+        let view = self.tabs.active().view;
+        let mut entries: Vec<PaletteEntry> = Action::all()
+            .into_iter()
+            .filter(|action| !matches!(action, Action::CommandPalette | Action::OpenFavorite(_)))
+            .filter(|action| action.context() != Context::Folders || view == ViewTab::Folders)
+            .map(|action| PaletteEntry {
+                label: action.label(),
+                context: action.context().label().to_string(),
+                keys: self.keymap.bindings_for(action).join(", "),
+                command: PaletteCommand::Run(action),
+            })
+            .collect();
+
+        entries.extend(self.bookmarks.entries().iter().enumerate().map(|(index, bookmark)| PaletteEntry {
+            label: format!("Go to bookmark {}", bookmark.name),
+            context: "Favorites".to_string(),
+            keys: if index < Action::FAVORITE_SLOTS {
+                self.keymap.bindings_for(Action::OpenFavorite(index)).join(", ")
+            } else {
+                String::new()
+            },
+            command: PaletteCommand::OpenBookmark(index),
+        }));
+
+        entries.extend(self.tag_db.get_all_tags().unwrap_or_default().into_iter().map(|tag| PaletteEntry {
+            label: format!("Filter by tag {}", tag.name),
+            context: "Tags".to_string(),
+            keys: String::new(),
+            command: PaletteCommand::FilterByTag(tag.name),
+        }));
+
+        if let Some(file) = self.selected_entry.as_ref().filter(|file| !file.is_dir()) {
+            entries.extend(self.file_associations.apps().into_iter().map(|app| PaletteEntry {
+                label: format!("Open with {}", app),
+                context: format!("Selected: {}", file.name),
+                keys: String::new(),
+                command: PaletteCommand::OpenWith(app),
+            }));
+        }
+
+        entries
+    }
+
+    fn execute_palette_command(&mut self, command: PaletteCommand, ctx: &egui::Context) {
+        match command {
+            PaletteCommand::Run(action) => self.run_action(action, ctx),
+            PaletteCommand::OpenBookmark(index) => self.open_bookmark(index),
+            PaletteCommand::FilterByTag(tag) => {
+                let tab = self.tabs.active_mut();
+                tab.view = ViewTab::Tags;
+                tab.tag_selected = Some(tag);
+                tab.kind_selected = None;
+                tab.selected_file_index = None;
+            }
+            PaletteCommand::OpenWith(app) => {
+                if let Some(file) = &self.selected_entry {
+                    let _ = self.file_associations.open_file_with(&file.path, &app);
+                }
+            }
         }
     }
 
+    /// Collapses the directory tree down to the path of the current folder.
+    fn collapse_tree_to_current(&mut self) {
+        self.expanded_directories.clear();
+        let mut path_to_expand = self.tabs.active().folder_current_path.clone();
+
+        while let Some(parent) = path_to_expand.parent() {
+            let parent_path = parent.to_path_buf();
+            if parent_path.starts_with(&self.tree_root_path) {
+                if parent_path == self.tree_root_path {
+                    self.expanded_directories.insert(self.tree_root_path.clone());
+                    break;
+                } else {
+                    self.expanded_directories.insert(parent_path.clone());
+                }
+            }
+            path_to_expand = parent_path;
+            if path_to_expand == self.tree_root_path {
+                break;
+            }
+        }
+    }
+
+    fn navigate_history(&mut self, offset: isize) {
+        if let Some(location) = self.history.go(offset) {
+            location.apply_to(self.tabs.active_mut());
+        }
+    }
+
+    fn add_current_to_favorites(&mut self) {
+        let tab = self.tabs.active();
+        let bookmark = if !tab.search_query.is_empty() {
+            let scope = if tab.view == ViewTab::Folders && self.directory_search_mode {
+                Some(tab.folder_current_path.clone())
+            } else {
+                None
+            };
+            Bookmark {
+                name: tab.search_query.clone(),
+                target: BookmarkTarget::Query {
+                    query: tab.search_query.clone(),
+                    scope,
+                },
+            }
+        } else if let (ViewTab::Tags, Some(tag)) = (tab.view, &tab.tag_selected) {
+            Bookmark {
+                name: tag.clone(),
+                target: BookmarkTarget::Tag { tag: tag.clone() },
+            }
+        } else {
+            Bookmark {
+                name: tab.title(),
+                target: BookmarkTarget::Folder {
+                    path: tab.folder_current_path.clone(),
+                },
+            }
+        };
+        self.bookmarks.add(bookmark);
+    }
+
+    fn open_bookmark(&mut self, index: usize) {
+        let Some(bookmark) = self.bookmarks.get(index) else {
+            return;
+        };
+        let tab = self.tabs.active_mut();
+        match &bookmark.target {
+            BookmarkTarget::Folder { path } => {
+                tab.view = ViewTab::Folders;
+                tab.folder_current_path = path.clone();
+                tab.search_query.clear();
+            }
+            BookmarkTarget::Tag { tag } => {
+                tab.view = ViewTab::Tags;
+                tab.tag_selected = Some(tag.clone());
+                tab.search_query.clear();
+            }
+            BookmarkTarget::Query { query, scope } => {
+                tab.view = ViewTab::Folders;
+                if let Some(scope) = scope {
+                    tab.folder_current_path = scope.clone();
+                }
+                tab.search_query = query.clone();
+                self.directory_search_mode = scope.is_some();
+            }
+        }
+        tab.selected_file_index = None;
+    }
+
+    fn handle_favorite_action(&mut self, action: FavoriteAction) {
+        match action {
+            FavoriteAction::Open(index) => self.open_bookmark(index),
+            FavoriteAction::StartRename(index) => {
+                if let Some(bookmark) = self.bookmarks.get(index) {
+                    self.favorite_renaming = Some((index, bookmark.name.clone()));
+                }
+            }
+            FavoriteAction::Rename(index, name) => {
+                self.bookmarks.rename(index, &name);
+                self.favorite_renaming = None;
+            }
+            FavoriteAction::CancelRename => self.favorite_renaming = None,
+            FavoriteAction::Move(from, to) => self.bookmarks.move_entry(from, to),
+            FavoriteAction::Remove(index) => self.bookmarks.remove(index),
+        }
+    }
+
+    /// Re-applies the theme when the setting or the system appearance
+    /// changed; eframe resets the visuals itself when the system one does.
+    fn apply_theme(&mut self, ctx: &egui::Context, system_dark: bool) {
+        let key = (self.settings.appearance.theme.clone(), system_dark);
+        if self.applied_theme.as_ref() != Some(&key) {
+            theme::apply(ctx, self.themes.resolve(&key.0, system_dark));
+            self.applied_theme = Some(key);
+        }
+    }
+
+    fn open_settings(&mut self) {
+        if self.settings_draft.is_none() {
+            self.settings_draft = Some(SettingsDraft::new(&self.settings, self.keymap.preset()));
+        }
+    }
+
+    /// Walks every configured index root on a background thread.
+    fn start_indexing(&mut self) {
+        if self.is_indexing.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        if let Some(handle) = self.indexing_thread.take() {
+            let _ = handle.join();
+        }
+
+        let is_indexing = self.is_indexing.clone();
+        let last_index_stats = self.last_index_stats.clone();
+        let indexer = self.indexer.clone();
+        let roots = self.settings.index.roots.clone();
+        indexer.set_roots(roots.clone());
+        indexer.begin_run(roots.len());
+
+        self.indexing_thread = Some(std::thread::spawn(move || {
+            let mut total = IndexStats::default();
+            for root in &roots {
+                if indexer.is_cancelled() {
+                    total.cancelled = true;
+                    break;
+                }
+                log::info!("Indexing {}", root.path.display());
+                match indexer.index_directory_with_depth(root) {
+                    Ok(stats) => total.merge(stats),
+                    Err(e) => notifications::error!(format!("Could not index root: {}", e), Some(&root.path)),
+                }
+            }
+            log::info!(
+                "Indexing {}: {} entries ({} changed, {} removed) in {:.1}s",
+                if total.cancelled { "cancelled" } else { "finished" },
+                total.entries,
+                total.written,
+                total.removed,
+                total.elapsed.as_secs_f64()
+            );
+            *last_index_stats.lock().unwrap() = Some(total);
+            is_indexing.store(false, Ordering::Relaxed);
+        }));
+    }
+
+    fn handle_indexing_action(&mut self, action: IndexingAction) {
+        match action {
+            IndexingAction::ShowPanel => self.show_indexing_panel = true,
+            IndexingAction::Cancel => self.indexer.cancel(),
+            IndexingAction::Reindex => self.start_indexing(),
+            IndexingAction::Close => self.show_indexing_panel = false,
+        }
+    }
+
+    fn handle_notification_action(&mut self, action: NotificationAction) {
+        match action {
+            NotificationAction::ShowPanel => self.show_notifications = true,
+            NotificationAction::DismissToast(index) => self.notifications.dismiss_toast(index),
+            NotificationAction::Clear => self.notifications.clear(),
+            NotificationAction::Close => self.show_notifications = false,
+            NotificationAction::ShowLog => self.show_log(),
+        }
+    }
+
+    fn show_log(&mut self) {
+        if self.log_viewer.is_none() {
+            self.log_viewer = Some(LogViewer::default());
+        }
+    }
+
+    fn start_saving_search(&mut self) {
+        let tab = self.tabs.active();
+        let scope = match (tab.view, &tab.tag_selected) {
+            (ViewTab::Tags, Some(tag)) => SmartScope::Tag { tag: tag.clone() },
+            (ViewTab::Folders, _) if self.directory_search_mode => SmartScope::Directory {
+                path: tab.folder_current_path.clone(),
+            },
+            _ => SmartScope::Everywhere,
+        };
+        self.smart_folder_editor = Some(SmartFolderDraft::new(&tab.search_query, scope));
+    }
+
+    fn open_smart_folder(&mut self, name: &str) {
+        let tab = self.tabs.active_mut();
+        tab.view = ViewTab::Smart;
+        tab.smart_folder = Some(name.to_string());
+        tab.search_query.clear();
+        tab.selected_file_index = None;
+    }
+
+    fn handle_smart_folder_action(&mut self, action: SmartFolderAction) {
+        match action {
+            SmartFolderAction::Open(name) => self.open_smart_folder(&name),
+            SmartFolderAction::Edit(name) => {
+                if let Some(folder) = self.smart_folders.get(&name) {
+                    self.smart_folder_editor = Some(SmartFolderDraft::from_folder(folder));
+                }
+            }
+            SmartFolderAction::Export(name) => match self.smart_folders.export(&name) {
+                Ok(path) => {
+                    notifications::info!(
+                        format!("Exported smart folder {:?} to {}", name, path.display()),
+                        Some(&path)
+                    );
+                }
+                Err(e) => notifications::error!(format!("Could not export smart folder {:?}: {}", name, e), None),
+            },
+            SmartFolderAction::Delete(name) => {
+                self.smart_folders.remove(&name);
+                let tab = self.tabs.active_mut();
+                if tab.smart_folder.as_deref() == Some(name.as_str()) {
+                    tab.smart_folder = None;
+                }
+            }
+        }
+    }
+
+    fn open_tab(&mut self) {
+        let current = self.tabs.active();
+        let mut tab = Tab::new(current.folder_current_path.clone());
+        tab.view = current.view;
+        tab.tag_selected = current.tag_selected.clone();
+        tab.kind_selected = current.kind_selected;
+        self.tabs.open(tab);
+    }
+
     fn create_directory_in_current(&mut self, name: &str) {
-        let mut path = self.folder_current_path.clone();
+        let mut path = self.tabs.active().folder_current_path.clone();
         path.push(name);
         if let Err(e) = fs::create_dir(&path) {
-            eprintln!("Error creating directory {:?}: {}", path, e);
+            notifications::error!(format!("Could not create folder: {}", e), Some(&path));
         } else {
             self.refresh_current_directory();
         }
     }
 
     fn create_file_in_current(&mut self, name: &str) {
-        let mut path = self.folder_current_path.clone();
+        let mut path = self.tabs.active().folder_current_path.clone();
         path.push(name);
-        match File::create(&path) {
-            Ok(_) => {
+        match self.filesystems.for_path(&path).write(&path, &[]) {
+            Ok(()) => {
                 self.refresh_current_directory();
             }
             Err(e) => {
-                eprintln!("Error creating file {:?}: {}", path, e);
+                notifications::error!(format!("Could not create file: {}", e), Some(&path));
+            }
+        }
+    }
+
+    /// Copies `file` out of its archive into the folder holding the archive.
+    fn extract_from_archive(&mut self, file: &FileEntry) {
+        let Some((archive, inner)) = archive::split(&file.path) else {
+            return;
+        };
+        let Some(dir) = archive.parent().map(Path::to_path_buf) else {
+            return;
+        };
+        let dest = dir.join(file.path.file_name().unwrap_or_default());
+        match archive::extract_entry(&archive, &inner, &dest) {
+            Ok(()) => {
+                notifications::info!(format!("Extracted {}", file.name), Some(&dest));
+                let indexer = self.indexer.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = indexer.index_directory_shallow(&dir) {
+                        notifications::error!(format!("Could not index folder: {}", e), Some(&dir));
+                    }
+                });
+            }
+            Err(e) => notifications::error!(format!("Could not extract file: {}", e), Some(&file.path)),
+        }
+    }
+
+    fn show_properties(&mut self, file: FileEntry) {
+        let vfs = self.filesystems.for_path(&file.path);
+        self.properties = Some(Properties::new(file, vfs, &self.tag_db));
+    }
+
+    fn toggle_mark(&mut self, file: FileEntry) {
+        if self.marked.remove(&file.path).is_none() {
+            self.marked.insert(file.path.clone(), file);
+        }
+    }
+
+    /// Opens the batch rename window for the marked entries, or for `file`
+    /// alone when it is not among them.
+    fn open_batch_rename(&mut self, file: Option<FileEntry>) {
+        let files: Vec<FileEntry> = match file {
+            Some(file) if !self.marked.contains_key(&file.path) => vec![file],
+            _ => self.marked.values().cloned().collect(),
+        };
+        if !files.is_empty() {
+            self.batch_rename = Some(BatchRenameDraft::new(files));
+        }
+    }
+
+    fn apply_batch_rename(&mut self, plan: &[batch_rename::PlannedRename]) {
+        let Some(first) = plan.first() else {
+            return;
+        };
+        match batch_rename::apply(self.filesystems.for_path(&first.from).as_ref(), plan) {
+            Ok(renamed) => {
+                notifications::info!(format!("Renamed {} items", renamed), first.from.parent());
+                self.batch_rename = None;
+                self.marked.clear();
+                self.filesystems.invalidate();
+                let dirs: HashSet<PathBuf> = plan
+                    .iter()
+                    .filter_map(|rename| rename.from.parent())
+                    .filter(|dir| matches!(vfs::Location::of_path(dir), vfs::Location::Local(_)))
+                    .map(Path::to_path_buf)
+                    .collect();
+                for dir in dirs {
+                    let indexer = self.indexer.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = indexer.index_directory_shallow(&dir) {
+                            notifications::error!(format!("Could not index folder: {}", e), Some(&dir));
+                        }
+                    });
+                }
             }
+            Err(e) => notifications::error!(format!("Could not rename: {}", e), first.from.parent()),
         }
     }
 
+    fn delete_permanently(&mut self, file: &FileEntry) {
+        match self.filesystems.for_path(&file.path).remove(&file.path) {
+            Ok(()) => {
+                notifications::info!(format!("Deleted {}", file.name), Some(&file.path));
+                self.filesystems.invalidate();
+            }
+            Err(e) => notifications::error!(format!("Could not delete file: {}", e), Some(&file.path)),
+        }
+    }
+
+    /// Re-reads the current folder into the index; archives and the trash
+    /// are listed from their backend instead.
     fn refresh_current_directory(&mut self) {
-        let path_to_index = self.folder_current_path.clone();
+        let path_to_index = self.tabs.active().folder_current_path.clone();
+        if !matches!(vfs::Location::of_path(&path_to_index), vfs::Location::Local(_)) {
+            self.filesystems.invalidate();
+            return;
+        }
         let indexer = self.indexer.clone();
         std::thread::spawn(move || {
             if let Err(e) = indexer.index_directory_shallow(&path_to_index) {
-                eprintln!("Error indexing directory after create: {}", e);
+                notifications::error!(format!("Could not index folder: {}", e), Some(&path_to_index));
             }
         });
     }
@@ -0,0 +1,319 @@
+//! Zip and tar archives browsed as folders. A path inside an archive is the
+//! archive's own path followed by the entry's path, e.g.
+//! `/home/me/photos.zip/2024/beach.jpg`, so tabs, history and bookmarks
+//! handle it like any other folder.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Component, Path, PathBuf};
+
+use crate::file_kind;
+use crate::settings;
+use crate::tag_db::{DirectoryScan, FileEntry, FileMetadata, FileType, IndexedFile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// Recognises an archive by its file name.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(ArchiveFormat::TarZst)
+        } else {
+            None
+        }
+    }
+}
+
+/// True for regular files whose name marks them as a supported archive.
+pub fn is_archive(entry: &FileEntry) -> bool {
+    entry.file_type == FileType::File && ArchiveFormat::detect(&entry.path).is_some()
+}
+
+/// Splits a path at the archive it points into: the archive file and the
+/// path inside it, which is empty for the archive's top level. `None` for
+/// paths that are not inside an archive. Only ancestors named like an
+/// archive are checked on disk, so this is cheap for ordinary paths.
+pub fn split(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    path.ancestors()
+        .filter(|ancestor| ArchiveFormat::detect(ancestor).is_some())
+        .find(|ancestor| ancestor.is_file())
+        .map(|archive| {
+            let inner = path.strip_prefix(archive).unwrap_or(Path::new("")).to_path_buf();
+            (archive.to_path_buf(), inner)
+        })
+}
+
+/// True for paths strictly inside an archive, as opposed to the archive file.
+/// Only ancestors named like archives are looked up on disk.
+pub fn is_inside_archive(path: &Path) -> bool {
+    path.parent().and_then(split).is_some()
+}
+
+/// One entry of an archive listing.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// Relative to the archive root, without `.` or `..` components.
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: i64,
+    pub mode: u32,
+}
+
+/// Drops `.`, `..`, root and prefix components, so entries can never point
+/// outside the archive.
+fn clean_entry_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect()
+}
+
+fn zip_time(time: Option<zip::DateTime>) -> i64 {
+    time.and_then(|time| {
+        chrono::NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+            .and_hms_opt(time.hour().into(), time.minute().into(), time.second().into())
+    })
+    .map_or(0, |time| time.and_utc().timestamp())
+}
+
+fn open_tar(path: &Path, format: ArchiveFormat) -> io::Result<tar::Archive<Box<dyn Read>>> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = match format {
+        ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(file)),
+        ArchiveFormat::TarZst => Box::new(zstd::stream::read::Decoder::new(file)?),
+        _ => Box::new(file),
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+/// Lists every file and folder of `archive`, adding folders that only
+/// appear as part of an entry's path. Links and special files are left out,
+/// since they cannot be opened or extracted from the archive.
+pub fn read_entries(archive: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    let format = ArchiveFormat::detect(archive)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a supported archive"))?;
+
+    let mut entries: BTreeMap<PathBuf, ArchiveEntry> = BTreeMap::new();
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+            for index in 0..zip.len() {
+                let file = zip.by_index_raw(index)?;
+                if file.is_symlink() {
+                    continue;
+                }
+                let Some(path) = file.enclosed_name() else {
+                    continue;
+                };
+                let path = clean_entry_path(&path);
+                entries.insert(path.clone(), ArchiveEntry {
+                    path,
+                    is_dir: file.is_dir(),
+                    size: file.size(),
+                    modified: zip_time(file.last_modified()),
+                    mode: file.unix_mode().unwrap_or(0o644),
+                });
+            }
+        }
+        _ => {
+            let mut tar = open_tar(archive, format)?;
+            for entry in tar.entries()? {
+                let entry = entry?;
+                let header = entry.header();
+                if !header.entry_type().is_file() && !header.entry_type().is_dir() {
+                    continue;
+                }
+                let path = clean_entry_path(&entry.path()?);
+                entries.insert(path.clone(), ArchiveEntry {
+                    path,
+                    is_dir: header.entry_type().is_dir(),
+                    size: header.size().unwrap_or(0),
+                    modified: header.mtime().map_or(0, |time| time as i64),
+                    mode: header.mode().unwrap_or(0o644),
+                });
+            }
+        }
+    }
+    entries.remove(Path::new(""));
+
+    let implied: Vec<PathBuf> = entries
+        .keys()
+        .flat_map(|path| path.ancestors().skip(1))
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect();
+    for path in implied {
+        entries.entry(path.clone()).or_insert(ArchiveEntry {
+            path,
+            is_dir: true,
+            size: 0,
+            modified: 0,
+            mode: 0o755,
+        });
+    }
+    Ok(entries.into_values().collect())
+}
+
+/// The entry as a `FileEntry` at its virtual path under `archive`.
+pub fn to_file_entry(archive: &Path, entry: &ArchiveEntry) -> FileEntry {
+    let path = archive.join(&entry.path);
+    let mut metadata = FileMetadata {
+        mode: entry.mode,
+        ..FileMetadata::default()
+    };
+    if !entry.is_dir {
+        metadata.extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        let (mime, kind) = file_kind::classify_by_name(metadata.extension.as_deref(), entry.mode);
+        metadata.mime = mime;
+        metadata.kind = Some(kind);
+    }
+    FileEntry {
+        name: path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+        parent: path.parent().map(Path::to_path_buf),
+        path,
+        file_type: if entry.is_dir { FileType::Directory } else { FileType::File },
+        size: entry.size,
+        modified: entry.modified,
+        ignored: false,
+        link: None,
+        metadata,
+    }
+}
+
+/// The archive's folders as directory scans for the indexer, so search
+/// finds files inside it. The archive's top level is listed under the
+/// archive's own path.
+pub fn directory_scans(archive: &Path) -> io::Result<Vec<DirectoryScan>> {
+    let mut by_dir: BTreeMap<PathBuf, Vec<IndexedFile>> = BTreeMap::new();
+    by_dir.insert(archive.to_path_buf(), Vec::new());
+    for entry in read_entries(archive)? {
+        let file = to_file_entry(archive, &entry);
+        if entry.is_dir {
+            by_dir.entry(file.path.clone()).or_default();
+        }
+        let dir = file.parent.clone().unwrap_or_else(|| archive.to_path_buf());
+        by_dir.entry(dir).or_default().push(IndexedFile {
+            entry: file,
+            tags: Vec::new(),
+        });
+    }
+    Ok(by_dir
+        .into_iter()
+        .map(|(dir, entries)| DirectoryScan {
+            dir,
+            entries,
+            complete: true,
+        })
+        .collect())
+}
+
+/// Streams the file at `inner` inside `archive` to `dest`, which must not
+/// exist. A partly written `dest` is removed again if the copy fails.
+pub fn extract_entry(archive: &Path, inner: &Path, dest: &Path) -> io::Result<()> {
+    let mut created = false;
+    let result = copy_entry(archive, inner, || {
+        let file = File::options().write(true).create_new(true).open(dest)?;
+        created = true;
+        Ok(file)
+    });
+    if result.is_err() && created {
+        let _ = std::fs::remove_file(dest);
+    }
+    result
+}
+
+/// The contents of the file at `inner` inside `archive`.
+pub fn read_entry(archive: &Path, inner: &Path) -> io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    copy_entry(archive, inner, || Ok(&mut contents))?;
+    Ok(contents)
+}
+
+/// Streams the file at `inner` into the writer `open` returns, which is
+/// only called once the entry has been found.
+fn copy_entry<W: Write>(archive: &Path, inner: &Path, open: impl FnOnce() -> io::Result<W>) -> io::Result<()> {
+    let format = ArchiveFormat::detect(archive)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a supported archive"))?;
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+            for index in 0..zip.len() {
+                let mut file = zip.by_index(index)?;
+                if file.enclosed_name().is_some_and(|path| clean_entry_path(&path) == inner) && file.is_file() {
+                    io::copy(&mut file, &mut open()?)?;
+                    return Ok(());
+                }
+            }
+        }
+        _ => {
+            let mut tar = open_tar(archive, format)?;
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                if entry.header().entry_type().is_file() && clean_entry_path(&entry.path()?) == inner {
+                    io::copy(&mut entry, &mut open()?)?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not a file in the archive", inner.display())))
+}
+
+/// The scratch folder for extracted files: private to the user, created
+/// with mode 0700, in `$XDG_RUNTIME_DIR` when there is one and under
+/// `~/.fms` otherwise. A shared folder such as `/tmp` would let other users
+/// plant symlinks where files get extracted.
+fn preview_dir() -> io::Result<PathBuf> {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| settings::home_dir().map(|home| home.join(".fms")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no private folder to extract into"))?;
+    let dir = base.join("fms-preview");
+    std::fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+    let metadata = std::fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a private folder", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+/// Extracts a file inside an archive to a scratch folder so it can be opened
+/// with its usual application. Earlier copies are replaced.
+pub fn extract_for_preview(path: &Path) -> io::Result<PathBuf> {
+    let (archive, inner) = split(path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not inside an archive"))?;
+    let archive_name = archive.file_name().unwrap_or_default();
+    let dest = preview_dir()?.join(archive_name).join(&inner);
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match std::fs::remove_file(&dest) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    extract_entry(&archive, &inner, &dest)?;
+    Ok(dest)
+}
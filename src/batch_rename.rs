@@ -0,0 +1,556 @@
+//! Renaming many files at once from one rule. A rule edits each name's
+//! stem (find/replace, then case), lays it out through a template with
+//! counters and dates, and sets the extension. The resulting plan is
+//! checked for clashes before anything is touched, and applied all or
+//! nothing.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, TimeZone};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::tag_db::FileEntry;
+use crate::vfs::Vfs;
+
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaseChange {
+    #[default]
+    Keep,
+    Lower,
+    Upper,
+    /// The first letter of every word upper-case, the rest lower-case.
+    Title,
+}
+
+impl CaseChange {
+    pub const ALL: [CaseChange; 4] = [CaseChange::Keep, CaseChange::Lower, CaseChange::Upper, CaseChange::Title];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CaseChange::Keep => "Keep case",
+            CaseChange::Lower => "lower case",
+            CaseChange::Upper => "UPPER CASE",
+            CaseChange::Title => "Title Case",
+        }
+    }
+
+    fn apply(self, text: &str) -> String {
+        match self {
+            CaseChange::Keep => text.to_string(),
+            CaseChange::Lower => text.to_lowercase(),
+            CaseChange::Upper => text.to_uppercase(),
+            CaseChange::Title => {
+                let mut title = String::with_capacity(text.len());
+                let mut word_start = true;
+                for c in text.chars() {
+                    if word_start {
+                        title.extend(c.to_uppercase());
+                    } else {
+                        title.extend(c.to_lowercase());
+                    }
+                    word_start = !c.is_alphanumeric() && c != '\'';
+                }
+                title
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ExtensionChange {
+    #[default]
+    Keep,
+    /// Written without the dot; empty removes the extension.
+    Replace(String),
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameRule {
+    /// Matched against the name without its extension; empty matches nothing.
+    pub find: String,
+    /// With `regex`, `$1` and `${name}` insert capture groups.
+    pub replace: String,
+    pub regex: bool,
+    pub case: CaseChange,
+    /// The new name without extension. `{name}` is the edited name, `{ext}`
+    /// the original extension, `{n}` or `{n:03}` a counter and `{date}` or
+    /// `{date:%Y%m%d}` the modification date.
+    pub template: String,
+    pub counter_start: usize,
+    pub extension: ExtensionChange,
+}
+
+impl Default for RenameRule {
+    fn default() -> Self {
+        RenameRule {
+            find: String::new(),
+            replace: String::new(),
+            regex: false,
+            case: CaseChange::Keep,
+            template: "{name}".to_string(),
+            counter_start: 1,
+            extension: ExtensionChange::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    Name,
+    Extension,
+    /// Zero-padded to this many digits.
+    Counter(usize),
+    Date(String),
+}
+
+fn parse_template(template: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        if open > 0 {
+            tokens.push(Token::Text(rest[..open].to_string()));
+        }
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed {{ in {:?}", template))?;
+        let placeholder = &rest[open + 1..open + close];
+        let (name, argument) = match placeholder.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (placeholder, None),
+        };
+        tokens.push(match (name, argument) {
+            ("name", None) => Token::Name,
+            ("ext", None) => Token::Extension,
+            ("n", None) => Token::Counter(0),
+            ("n", Some(width)) => Token::Counter(
+                width
+                    .parse()
+                    .map_err(|_| format!("{{n:{}}} needs a digit count such as {{n:03}}", width))?,
+            ),
+            ("date", format) => {
+                let format = format.unwrap_or(DEFAULT_DATE_FORMAT);
+                if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                    return Err(format!("invalid date format {:?}", format));
+                }
+                Token::Date(format.to_string())
+            }
+            _ => return Err(format!("unknown placeholder {{{}}}", placeholder)),
+        });
+        rest = &rest[open + close + 1..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest.to_string()));
+    }
+    Ok(tokens)
+}
+
+/// The name on disk, with any bytes that are not UTF-8 shown as U+FFFD.
+fn file_name(file: &FileEntry) -> Cow<'_, str> {
+    match file.path.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => Cow::Borrowed(&file.name),
+    }
+}
+
+/// Splits a file name into stem and extension. Folders, dot files such as
+/// `.bashrc` and names without a dot have no extension.
+fn split_name(name: &str, is_dir: bool) -> (&str, Option<&str>) {
+    if is_dir {
+        return (name, None);
+    }
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    }
+}
+
+/// A rule checked and ready to produce names.
+pub struct Renamer {
+    rule: RenameRule,
+    regex: Option<Regex>,
+    template: Vec<Token>,
+}
+
+impl Renamer {
+    pub fn new(rule: &RenameRule) -> Result<Self, String> {
+        let regex = match (rule.regex, rule.find.is_empty()) {
+            (true, false) => Some(Regex::new(&rule.find).map_err(|e| e.to_string())?),
+            _ => None,
+        };
+        Ok(Renamer {
+            rule: rule.clone(),
+            regex,
+            template: parse_template(&rule.template)?,
+        })
+    }
+
+    /// The new name of `file`, the `index`-th file of the batch.
+    pub fn new_name(&self, file: &FileEntry, index: usize) -> String {
+        let name = file_name(file);
+        let (stem, extension) = split_name(&name, file.is_dir());
+        let stem = match &self.regex {
+            Some(regex) => regex.replace_all(stem, self.rule.replace.as_str()).into_owned(),
+            None if !self.rule.find.is_empty() => stem.replace(&self.rule.find, &self.rule.replace),
+            None => stem.to_string(),
+        };
+        let stem = self.rule.case.apply(&stem);
+
+        let mut name = String::new();
+        for token in &self.template {
+            match token {
+                Token::Text(text) => name.push_str(text),
+                Token::Name => name.push_str(&stem),
+                Token::Extension => name.push_str(extension.unwrap_or_default()),
+                Token::Counter(width) => {
+                    name.push_str(&format!("{:0width$}", self.rule.counter_start + index, width = *width))
+                }
+                Token::Date(format) => {
+                    if let Some(modified) = Local.timestamp_opt(file.modified, 0).single() {
+                        name.push_str(&modified.format(format).to_string());
+                    }
+                }
+            }
+        }
+
+        let extension = match &self.rule.extension {
+            ExtensionChange::Keep => extension,
+            ExtensionChange::Replace(extension) => Some(extension.trim_start_matches('.')),
+            ExtensionChange::Remove => None,
+        };
+        match extension.filter(|extension| !extension.is_empty()) {
+            Some(extension) => format!("{}.{}", name, extension),
+            None => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    InvalidName(&'static str),
+    /// Another file of the batch gets the same name.
+    Duplicate,
+    /// A file outside the batch already has the name.
+    Exists,
+    /// The current name is not valid UTF-8, so a rule that edits it would
+    /// lose the bytes that cannot be shown.
+    NotUnicode,
+}
+
+impl Problem {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Problem::InvalidName(reason) => reason,
+            Problem::Duplicate => "another file gets the same name",
+            Problem::Exists => "a file with this name already exists",
+            Problem::NotUnicode => "the current name is not valid UTF-8 and can only be kept as it is",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedRename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub problem: Option<Problem>,
+}
+
+impl PlannedRename {
+    pub fn changes(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+fn invalid_reason(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        Some("the name is empty")
+    } else if name == "." || name == ".." {
+        Some("the name is reserved")
+    } else if name.contains('/') || name.contains('\0') {
+        Some("names cannot contain / or NUL")
+    } else if name.len() > 255 {
+        Some("the name is longer than 255 bytes")
+    } else {
+        None
+    }
+}
+
+/// The new path of every file, with what would stop it from being renamed.
+/// `exists` tells whether a path is already taken on disk.
+pub fn plan(files: &[FileEntry], renamer: &Renamer, exists: &dyn Fn(&Path) -> bool) -> Vec<PlannedRename> {
+    let mut planned: Vec<PlannedRename> = files
+        .iter()
+        .enumerate()
+        .map(|(index, file)| {
+            let name = renamer.new_name(file, index);
+            let parent = file.path.parent().unwrap_or(Path::new("/"));
+            // Names are edited as text, so a name that is not UTF-8 can only
+            // be left alone: its exact bytes are kept when the rule does not
+            // change it, and renaming it is refused otherwise.
+            let unicode = file.path.file_name().is_none_or(|name| name.to_str().is_some());
+            if !unicode && name == file_name(file) {
+                return PlannedRename {
+                    from: file.path.clone(),
+                    to: file.path.clone(),
+                    problem: None,
+                };
+            }
+            PlannedRename {
+                from: file.path.clone(),
+                to: parent.join(&name),
+                problem: match invalid_reason(&name) {
+                    Some(reason) => Some(Problem::InvalidName(reason)),
+                    None if !unicode => Some(Problem::NotUnicode),
+                    None => None,
+                },
+            }
+        })
+        .collect();
+
+    let mut targets: HashMap<PathBuf, usize> = HashMap::new();
+    for rename in &planned {
+        *targets.entry(rename.to.clone()).or_default() += 1;
+    }
+    let sources: HashSet<PathBuf> = planned.iter().map(|rename| rename.from.clone()).collect();
+    for rename in planned.iter_mut().filter(|rename| rename.problem.is_none()) {
+        if targets[&rename.to] > 1 {
+            rename.problem = Some(Problem::Duplicate);
+        } else if rename.changes()
+            && !sources.contains(&rename.to)
+            // A change of case only finds the file itself on case-insensitive disks.
+            && rename.to.to_string_lossy().to_lowercase() != rename.from.to_string_lossy().to_lowercase()
+            && exists(&rename.to)
+        {
+            rename.problem = Some(Problem::Exists);
+        }
+    }
+    planned
+}
+
+/// A scratch name next to `path`, unique within this process's batch.
+fn temporary_path(path: &Path, index: usize) -> PathBuf {
+    path.with_file_name(format!(".fms-rename-{}-{}", std::process::id(), index))
+}
+
+/// Renames every changed file of `plan`, or none of them: each file first
+/// moves to a scratch name so names can be swapped or shifted, and if any
+/// step fails the steps already taken are undone in reverse. Returns how
+/// many files were renamed.
+pub fn apply(vfs: &dyn Vfs, plan: &[PlannedRename]) -> io::Result<usize> {
+    if let Some(rename) = plan.iter().find(|rename| rename.problem.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: {}", rename.to.display(), rename.problem.as_ref().map_or("", Problem::describe)),
+        ));
+    }
+
+    let changes: Vec<&PlannedRename> = plan.iter().filter(|rename| rename.changes()).collect();
+    let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let result = (|| {
+        let temporary: Vec<PathBuf> = changes
+            .iter()
+            .enumerate()
+            .map(|(index, rename)| temporary_path(&rename.from, index))
+            .collect();
+        for (rename, temporary) in changes.iter().zip(&temporary) {
+            vfs.rename(&rename.from, temporary)?;
+            done.push((rename.from.clone(), temporary.clone()));
+        }
+        for (rename, temporary) in changes.iter().zip(&temporary) {
+            if vfs.stat(&rename.to).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", rename.to.display()),
+                ));
+            }
+            vfs.rename(temporary, &rename.to)?;
+            done.push((temporary.clone(), rename.to.clone()));
+        }
+        Ok(changes.len())
+    })();
+
+    result.map_err(|e| {
+        let failed: Vec<String> = done
+            .iter()
+            .rev()
+            .filter_map(|(from, to)| vfs.rename(to, from).err().map(|_| from.display().to_string()))
+            .collect();
+        if failed.is_empty() {
+            io::Error::new(e.kind(), format!("{}; nothing was renamed", e))
+        } else {
+            io::Error::new(e.kind(), format!("{}; could not restore {}", e, failed.join(", ")))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::memory::MemoryVfs;
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    fn vfs_with(files: &[&str]) -> MemoryVfs {
+        let vfs = MemoryVfs::new();
+        for file in files {
+            vfs.write(Path::new(file), file.as_bytes()).unwrap();
+        }
+        vfs
+    }
+
+    fn entries(vfs: &MemoryVfs, files: &[&str]) -> Vec<FileEntry> {
+        files.iter().map(|file| vfs.stat(Path::new(file)).unwrap()).collect()
+    }
+
+    fn plan_with(vfs: &MemoryVfs, files: &[FileEntry], rule: RenameRule) -> Vec<PlannedRename> {
+        let renamer = Renamer::new(&rule).unwrap();
+        plan(files, &renamer, &|path| vfs.stat(path).is_ok())
+    }
+
+    fn names(vfs: &MemoryVfs, dir: &str) -> Vec<String> {
+        let mut names: Vec<String> = vfs.list(Path::new(dir)).unwrap().into_iter().map(|f| f.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn template_counter_and_case() {
+        let vfs = vfs_with(&["/d/Beach Day.JPG", "/d/sunset.jpg"]);
+        let files = entries(&vfs, &["/d/Beach Day.JPG", "/d/sunset.jpg"]);
+        let rule = RenameRule {
+            case: CaseChange::Lower,
+            template: "{n:03} {name}".to_string(),
+            extension: ExtensionChange::Replace("jpeg".to_string()),
+            ..RenameRule::default()
+        };
+        let plan = plan_with(&vfs, &files, rule);
+        assert_eq!(plan[0].to, Path::new("/d/001 beach day.jpeg"));
+        assert_eq!(plan[1].to, Path::new("/d/002 sunset.jpeg"));
+        assert!(plan.iter().all(|rename| rename.problem.is_none()));
+
+        assert_eq!(apply(&vfs, &plan).unwrap(), 2);
+        assert_eq!(names(&vfs, "/d"), ["001 beach day.jpeg", "002 sunset.jpeg"]);
+    }
+
+    #[test]
+    fn folders_and_dot_files_have_no_extension() {
+        let vfs = vfs_with(&["/d/.bashrc"]);
+        vfs.create_dir_all(Path::new("/d/v1.2")).unwrap();
+        let files = entries(&vfs, &["/d/v1.2", "/d/.bashrc"]);
+        let rule = RenameRule {
+            template: "{name}-old".to_string(),
+            ..RenameRule::default()
+        };
+        let plan = plan_with(&vfs, &files, rule);
+        assert_eq!(plan[0].to, Path::new("/d/v1.2-old"));
+        assert_eq!(plan[1].to, Path::new("/d/.bashrc-old"));
+    }
+
+    #[test]
+    fn regex_replace_uses_capture_groups() {
+        let vfs = vfs_with(&["/d/IMG_2024_01.png"]);
+        let files = entries(&vfs, &["/d/IMG_2024_01.png"]);
+        let rule = RenameRule {
+            find: r"IMG_(\d+)_(\d+)".to_string(),
+            replace: "$1-$2".to_string(),
+            regex: true,
+            ..RenameRule::default()
+        };
+        assert_eq!(plan_with(&vfs, &files, rule)[0].to, Path::new("/d/2024-01.png"));
+    }
+
+    #[test]
+    fn flags_duplicates_existing_and_invalid_names() {
+        let vfs = vfs_with(&["/d/a.txt", "/d/b.txt", "/d/taken.txt"]);
+        let files = entries(&vfs, &["/d/a.txt", "/d/b.txt"]);
+
+        let same = RenameRule {
+            template: "same".to_string(),
+            ..RenameRule::default()
+        };
+        assert!(plan_with(&vfs, &files, same).iter().all(|r| r.problem == Some(Problem::Duplicate)));
+
+        let taken = RenameRule {
+            find: "a".to_string(),
+            replace: "taken".to_string(),
+            ..RenameRule::default()
+        };
+        assert_eq!(plan_with(&vfs, &files, taken)[0].problem, Some(Problem::Exists));
+
+        let slash = RenameRule {
+            template: "x/{name}".to_string(),
+            ..RenameRule::default()
+        };
+        assert!(matches!(plan_with(&vfs, &files, slash)[0].problem, Some(Problem::InvalidName(_))));
+    }
+
+    #[test]
+    fn swaps_names_within_the_batch() {
+        let vfs = vfs_with(&["/d/a", "/d/b"]);
+        let plan = vec![
+            PlannedRename {
+                from: PathBuf::from("/d/a"),
+                to: PathBuf::from("/d/b"),
+                problem: None,
+            },
+            PlannedRename {
+                from: PathBuf::from("/d/b"),
+                to: PathBuf::from("/d/a"),
+                problem: None,
+            },
+        ];
+        assert_eq!(apply(&vfs, &plan).unwrap(), 2);
+        assert_eq!(vfs.read(Path::new("/d/a")).unwrap(), b"/d/b");
+        assert_eq!(vfs.read(Path::new("/d/b")).unwrap(), b"/d/a");
+    }
+
+    #[test]
+    fn failed_batch_is_rolled_back() {
+        let vfs = vfs_with(&["/d/a", "/d/b"]);
+        let plan = vec![
+            PlannedRename {
+                from: PathBuf::from("/d/a"),
+                to: PathBuf::from("/d/x"),
+                problem: None,
+            },
+            PlannedRename {
+                from: PathBuf::from("/d/b"),
+                to: PathBuf::from("/d/y"),
+                problem: None,
+            },
+        ];
+        // Appears after the plan was checked.
+        vfs.write(Path::new("/d/y"), b"other").unwrap();
+
+        let error = apply(&vfs, &plan).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(names(&vfs, "/d"), ["a", "b", "y"]);
+        assert_eq!(vfs.read(Path::new("/d/a")).unwrap(), b"/d/a");
+        assert_eq!(vfs.read(Path::new("/d/y")).unwrap(), b"other");
+    }
+
+    #[test]
+    fn non_utf8_names_keep_their_bytes() {
+        let vfs = MemoryVfs::new();
+        let path = Path::new("/d").join(OsString::from_vec(b"caf\xe9.txt".to_vec()));
+        vfs.write(&path, b"").unwrap();
+        let files = vec![vfs.stat(&path).unwrap()];
+
+        let unchanged = plan_with(&vfs, &files, RenameRule::default());
+        assert_eq!(unchanged[0].to, path);
+        assert!(!unchanged[0].changes());
+        assert!(unchanged[0].problem.is_none());
+
+        let edited = RenameRule {
+            template: "new {name}".to_string(),
+            ..RenameRule::default()
+        };
+        assert_eq!(plan_with(&vfs, &files, edited)[0].problem, Some(Problem::NotUnicode));
+    }
+}
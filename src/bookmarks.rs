@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::notifications;
+use crate::settings::{contract_home, expand_home};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BookmarkTarget {
+    Folder {
+        #[serde(with = "crate::os_path::lossless")]
+        path: PathBuf,
+    },
+    Tag { tag: String },
+    Query {
+        query: String,
+        #[serde(default, with = "crate::os_path::lossless_option")]
+        scope: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    #[serde(flatten)]
+    pub target: BookmarkTarget,
+}
+
+impl Bookmark {
+    pub fn icon(&self) -> &'static str {
+        match self.target {
+            BookmarkTarget::Folder { .. } => "📁",
+            BookmarkTarget::Tag { .. } => "🏷",
+            BookmarkTarget::Query { .. } => "🔍",
+        }
+    }
+}
+
+pub struct Bookmarks {
+    entries: Vec<Bookmark>,
+    config_path: PathBuf,
+}
+
+impl Bookmarks {
+    pub fn new(config_dir: &Path) -> Self {
+        let config_path = config_dir.join("bookmarks.json");
+        let entries = Self::load_config(&config_path);
+
+        Bookmarks {
+            entries,
+            config_path,
+        }
+    }
+
+    fn load_config(config_path: &Path) -> Vec<Bookmark> {
+        if !config_path.exists() {
+            return Vec::new();
+        }
+
+        match std::fs::read_to_string(config_path) {
+            Ok(content) => match serde_json::from_str::<Vec<Bookmark>>(&content) {
+                Ok(mut entries) => {
+                    for entry in &mut entries {
+                        map_paths(&mut entry.target, expand_home);
+                    }
+                    entries
+                }
+                Err(e) => {
+                    notifications::error!(format!("Could not parse bookmarks: {}", e), Some(config_path));
+                    Vec::new()
+                }
+            },
+            Err(e) => {
+                notifications::error!(format!("Could not read bookmarks: {}", e), Some(config_path));
+                Vec::new()
+            }
+        }
+    }
+
+    /// Paths under `$HOME` are written as `~/...` so the file can be shared
+    /// between machines with different home directories.
+    fn save(&self) {
+        let portable: Vec<Bookmark> = self
+            .entries
+            .iter()
+            .cloned()
+            .map(|mut entry| {
+                map_paths(&mut entry.target, contract_home);
+                entry
+            })
+            .collect();
+
+        let result = serde_json::to_string_pretty(&portable)
+            .map_err(std::io::Error::from)
+            .and_then(|json| {
+                if let Some(parent) = self.config_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&self.config_path, json)
+            });
+        if let Err(e) = result {
+            notifications::error!(format!("Could not save bookmarks: {}", e), Some(&self.config_path));
+        }
+    }
+
+    pub fn entries(&self) -> &[Bookmark] {
+        &self.entries
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Bookmark> {
+        self.entries.get(index)
+    }
+
+    pub fn add(&mut self, bookmark: Bookmark) {
+        if self.entries.iter().any(|b| b.target == bookmark.target) {
+            return;
+        }
+        self.entries.push(bookmark);
+        self.save();
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+            self.save();
+        }
+    }
+
+    pub fn rename(&mut self, index: usize, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        if let Some(entry) = self.entries.get_mut(index) {
+            entry.name = name.to_string();
+            self.save();
+        }
+    }
+
+    pub fn move_entry(&mut self, from: usize, to: usize) {
+        if from < self.entries.len() && to < self.entries.len() && from != to {
+            let entry = self.entries.remove(from);
+            self.entries.insert(to, entry);
+            self.save();
+        }
+    }
+}
+
+fn map_paths(target: &mut BookmarkTarget, f: fn(&Path) -> PathBuf) {
+    match target {
+        BookmarkTarget::Folder { path } => *path = f(path),
+        BookmarkTarget::Query { scope: Some(scope), .. } => *scope = f(scope),
+        BookmarkTarget::Tag { .. } | BookmarkTarget::Query { scope: None, .. } => {}
+    }
+}
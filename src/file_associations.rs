@@ -1,8 +1,14 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
+use crate::archive;
+use crate::notifications;
+use crate::settings;
+use crate::tag_db::{TagDatabase, VisitKind};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Config {
     #[serde(flatten)]
@@ -11,26 +17,26 @@ struct Config {
 
 pub struct FileAssociations {
     associations: HashMap<String, String>,
-    config_path: PathBuf,
+    visit_log: Option<Arc<TagDatabase>>,
 }
 
 impl FileAssociations {
     pub fn new() -> Self {
-        let home_dir = std::env::var("HOME")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from("/"));
-        
-        let config_dir = home_dir.join(".fms");
-        let config_path = config_dir.join("apps.json");
+        let config_path = settings::config_dir().join("apps.json");
         
         let associations = Self::load_config(&config_path);
         
         FileAssociations {
             associations,
-            config_path,
+            visit_log: None,
         }
     }
     
+    /// Successful opens are recorded in `tag_db` for frecency ranking.
+    pub fn set_visit_log(&mut self, tag_db: Arc<TagDatabase>) {
+        self.visit_log = Some(tag_db);
+    }
+    
     fn load_config(config_path: &Path) -> HashMap<String, String> {
         if !config_path.exists() {
             if let Some(parent) = config_path.parent() {
@@ -44,19 +50,73 @@ impl FileAssociations {
                 match serde_json::from_str::<Config>(&content) {
                     Ok(config) => config.associations,
                     Err(e) => {
-                        eprintln!("Error parsing config file {}: {}", config_path.display(), e);
+                        notifications::error!(format!("Could not parse file associations: {}", e), Some(config_path));
                         HashMap::new()
                     }
                 }
             }
             Err(e) => {
-                eprintln!("Error reading config file {}: {}", config_path.display(), e);
+                notifications::error!(format!("Could not read file associations: {}", e), Some(config_path));
                 HashMap::new()
             }
         }
     }
     
+    /// Failures are also reported to the user, so callers may ignore them.
+    /// Files inside an archive are extracted to a scratch copy first.
     pub fn open_file(&self, file_path: &Path) -> std::io::Result<std::process::Output> {
+        let output = Self::openable_path(file_path).and_then(|path| self.launch(&path));
+        self.record_open(file_path, &output);
+        output
+    }
+
+    /// Opens `file_path` with `app_name`, ignoring the configured association.
+    pub fn open_file_with(&self, file_path: &Path, app_name: &str) -> std::io::Result<std::process::Output> {
+        let output = Self::openable_path(file_path).and_then(|path| {
+            Command::new("open")
+                .arg("-a")
+                .arg(app_name)
+                .arg(path)
+                .output()
+        });
+        self.record_open(file_path, &output);
+        output
+    }
+
+    /// The distinct applications named in `apps.json`, sorted.
+    pub fn apps(&self) -> Vec<String> {
+        let mut apps: Vec<String> = self.associations.values().cloned().collect();
+        apps.sort();
+        apps.dedup();
+        apps
+    }
+
+    fn openable_path(file_path: &Path) -> std::io::Result<PathBuf> {
+        if archive::is_inside_archive(file_path) {
+            archive::extract_for_preview(file_path)
+        } else {
+            Ok(file_path.to_path_buf())
+        }
+    }
+
+    fn record_open(&self, file_path: &Path, output: &std::io::Result<std::process::Output>) {
+        match output {
+            Ok(output) if output.status.success() => {
+                if let Some(tag_db) = &self.visit_log {
+                    if let Err(e) = tag_db.record_visit(file_path, VisitKind::Opened) {
+                        notifications::warning!(format!("Could not record visit: {}", e), Some(file_path));
+                    }
+                }
+            }
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                notifications::error!(format!("Could not open file: {}", stderr.trim()), Some(file_path));
+            }
+            Err(e) => notifications::error!(format!("Could not open file: {}", e), Some(file_path)),
+        }
+    }
+    
+    fn launch(&self, file_path: &Path) -> std::io::Result<std::process::Output> {
         let extension = file_path
             .extension()
             .and_then(|ext| ext.to_str())
@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+
+/// Bytes read from the start of a file for signature matching; enough to
+/// reach the tar header magic at offset 257.
+const SNIFF_LEN: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FileKind {
+    Document,
+    Image,
+    Audio,
+    Video,
+    Archive,
+    Code,
+    Executable,
+    Other,
+}
+
+impl FileKind {
+    pub const ALL: [FileKind; 8] = [
+        FileKind::Document,
+        FileKind::Image,
+        FileKind::Audio,
+        FileKind::Video,
+        FileKind::Archive,
+        FileKind::Code,
+        FileKind::Executable,
+        FileKind::Other,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FileKind::Document => "document",
+            FileKind::Image => "image",
+            FileKind::Audio => "audio",
+            FileKind::Video => "video",
+            FileKind::Archive => "archive",
+            FileKind::Code => "code",
+            FileKind::Executable => "executable",
+            FileKind::Other => "other",
+        }
+    }
+
+    /// Accepts the singular or plural name in any case, e.g. `Images`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        let name = name.strip_suffix('s').unwrap_or(&name);
+        FileKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FileKind::Document => "Documents",
+            FileKind::Image => "Images",
+            FileKind::Audio => "Audio",
+            FileKind::Video => "Videos",
+            FileKind::Archive => "Archives",
+            FileKind::Code => "Code",
+            FileKind::Executable => "Executables",
+            FileKind::Other => "Other",
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            FileKind::Document => "📃",
+            FileKind::Image => "🖼",
+            FileKind::Audio => "🎵",
+            FileKind::Video => "🎞",
+            FileKind::Archive => "📦",
+            FileKind::Code => "📜",
+            FileKind::Executable => "⚙",
+            FileKind::Other => "📄",
+        }
+    }
+}
+
+/// Classifies a regular file: the content signature wins when it is
+/// recognised, then the extension, then the executable bit.
+pub fn classify(path: &Path, extension: Option<&str>, mode: u32) -> (Option<String>, FileKind) {
+    classify_head(read_head(path).as_deref(), extension, mode)
+}
+
+/// Classifies by the extension and executable bit alone, for files whose
+/// contents cannot be read cheaply, such as entries inside an archive.
+pub fn classify_by_name(extension: Option<&str>, mode: u32) -> (Option<String>, FileKind) {
+    classify_head(None, extension, mode)
+}
+
+fn classify_head(head: Option<&[u8]>, extension: Option<&str>, mode: u32) -> (Option<String>, FileKind) {
+    let sniffed = head.and_then(|head| sniff(head, extension));
+    let (mime, kind) = match sniffed.or_else(|| extension.and_then(from_extension)) {
+        Some((mime, kind)) => (Some(mime.to_string()), kind),
+        None => (None, FileKind::Other),
+    };
+
+    if kind == FileKind::Other && mode & 0o111 != 0 {
+        return (mime, FileKind::Executable);
+    }
+    (mime, kind)
+}
+
+fn read_head(path: &Path) -> Option<Vec<u8>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(SNIFF_LEN as u64).read_to_end(&mut head))
+        .ok()?;
+    Some(head)
+}
+
+fn sniff(head: &[u8], extension: Option<&str>) -> Option<(&'static str, FileKind)> {
+    use FileKind::*;
+
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+
+    let found = if at(0, b"%PDF-") {
+        ("application/pdf", Document)
+    } else if at(0, b"{\\rtf") {
+        ("application/rtf", Document)
+    } else if at(0, b"%!PS") {
+        ("application/postscript", Document)
+    } else if at(0, &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]) {
+        ("application/x-ole-storage", Document)
+    } else if at(0, b"\x89PNG\r\n\x1a\n") {
+        ("image/png", Image)
+    } else if at(0, &[0xFF, 0xD8, 0xFF]) {
+        ("image/jpeg", Image)
+    } else if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        ("image/gif", Image)
+    } else if at(0, b"RIFF") && at(8, b"WEBP") {
+        ("image/webp", Image)
+    } else if at(0, b"II*\0") || at(0, b"MM\0*") {
+        ("image/tiff", Image)
+    } else if at(0, &[0x00, 0x00, 0x01, 0x00]) && extension == Some("ico") {
+        ("image/vnd.microsoft.icon", Image)
+    } else if at(0, b"BM") && extension == Some("bmp") {
+        ("image/bmp", Image)
+    } else if at(0, b"ID3") || at(0, &[0xFF, 0xFB]) || at(0, &[0xFF, 0xF3]) {
+        ("audio/mpeg", Audio)
+    } else if at(0, b"fLaC") {
+        ("audio/flac", Audio)
+    } else if at(0, b"OggS") {
+        ("audio/ogg", Audio)
+    } else if at(0, b"RIFF") && at(8, b"WAVE") {
+        ("audio/wav", Audio)
+    } else if at(0, b"RIFF") && at(8, b"AVI ") {
+        ("video/x-msvideo", Video)
+    } else if at(4, b"ftyp") {
+        match head.get(8..12) {
+            Some(b"M4A ") | Some(b"M4B ") => ("audio/mp4", Audio),
+            Some(b"qt  ") => ("video/quicktime", Video),
+            Some(b"heic") | Some(b"heix") | Some(b"mif1") => ("image/heic", Image),
+            Some(b"avif") => ("image/avif", Image),
+            _ => ("video/mp4", Video),
+        }
+    } else if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        if extension == Some("webm") {
+            ("video/webm", Video)
+        } else {
+            ("video/x-matroska", Video)
+        }
+    } else if at(0, b"PK\x03\x04") {
+        // Office, OpenDocument, iWork, EPUB and Java archives are all zip files.
+        match extension {
+            Some("key") => ("application/vnd.apple.keynote", Document),
+            Some(ext @ ("docx" | "xlsx" | "pptx" | "odt" | "ods" | "odp" | "epub" | "pages" | "numbers" | "jar")) => {
+                from_extension(ext).unwrap_or(("application/zip", Archive))
+            }
+            _ => ("application/zip", Archive),
+        }
+    } else if at(0, &[0x1F, 0x8B]) {
+        ("application/gzip", Archive)
+    } else if at(0, b"BZh") {
+        ("application/x-bzip2", Archive)
+    } else if at(0, &[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+        ("application/x-xz", Archive)
+    } else if at(0, &[0x28, 0xB5, 0x2F, 0xFD]) {
+        ("application/zstd", Archive)
+    } else if at(0, &[b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C]) {
+        ("application/x-7z-compressed", Archive)
+    } else if at(0, b"Rar!\x1a\x07") {
+        ("application/vnd.rar", Archive)
+    } else if at(257, b"ustar") {
+        ("application/x-tar", Archive)
+    } else if at(0, b"\x7fELF") {
+        ("application/x-executable", Executable)
+    } else if at(0, &[0xCF, 0xFA, 0xED, 0xFE])
+        || at(0, &[0xCE, 0xFA, 0xED, 0xFE])
+        || at(0, &[0xFE, 0xED, 0xFA, 0xCF])
+        || at(0, &[0xFE, 0xED, 0xFA, 0xCE])
+    {
+        ("application/x-mach-binary", Executable)
+    } else if at(0, &[0xCA, 0xFE, 0xBA, 0xBE]) {
+        // Universal Mach-O binaries and Java classes share this magic.
+        if extension == Some("class") {
+            ("application/java-vm", Code)
+        } else {
+            ("application/x-mach-binary", Executable)
+        }
+    } else if at(0, b"MZ") {
+        ("application/vnd.microsoft.portable-executable", Executable)
+    } else if at(0, b"SQLite format 3\0") {
+        ("application/vnd.sqlite3", Other)
+    } else if at(0, b"#!") {
+        ("text/x-script", Code)
+    } else {
+        return None;
+    };
+    Some(found)
+}
+
+fn from_extension(extension: &str) -> Option<(&'static str, FileKind)> {
+    use FileKind::*;
+
+    let found = match extension {
+        "pdf" => ("application/pdf", Document),
+        "txt" | "text" | "log" => ("text/plain", Document),
+        "md" | "markdown" => ("text/markdown", Document),
+        "rtf" => ("application/rtf", Document),
+        "doc" => ("application/msword", Document),
+        "docx" => ("application/vnd.openxmlformats-officedocument.wordprocessingml.document", Document),
+        "xls" => ("application/vnd.ms-excel", Document),
+        "xlsx" => ("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", Document),
+        "ppt" => ("application/vnd.ms-powerpoint", Document),
+        "pptx" => ("application/vnd.openxmlformats-officedocument.presentationml.presentation", Document),
+        "odt" => ("application/vnd.oasis.opendocument.text", Document),
+        "ods" => ("application/vnd.oasis.opendocument.spreadsheet", Document),
+        "odp" => ("application/vnd.oasis.opendocument.presentation", Document),
+        "pages" => ("application/vnd.apple.pages", Document),
+        "numbers" => ("application/vnd.apple.numbers", Document),
+        "epub" => ("application/epub+zip", Document),
+        "csv" => ("text/csv", Document),
+        "png" => ("image/png", Image),
+        "jpg" | "jpeg" => ("image/jpeg", Image),
+        "gif" => ("image/gif", Image),
+        "webp" => ("image/webp", Image),
+        "bmp" => ("image/bmp", Image),
+        "tif" | "tiff" => ("image/tiff", Image),
+        "svg" => ("image/svg+xml", Image),
+        "heic" => ("image/heic", Image),
+        "ico" => ("image/vnd.microsoft.icon", Image),
+        "mp3" => ("audio/mpeg", Audio),
+        "flac" => ("audio/flac", Audio),
+        "ogg" | "oga" => ("audio/ogg", Audio),
+        "wav" => ("audio/wav", Audio),
+        "m4a" => ("audio/mp4", Audio),
+        "aac" => ("audio/aac", Audio),
+        "mp4" | "m4v" => ("video/mp4", Video),
+        "mov" => ("video/quicktime", Video),
+        "mkv" => ("video/x-matroska", Video),
+        "webm" => ("video/webm", Video),
+        "avi" => ("video/x-msvideo", Video),
+        "zip" => ("application/zip", Archive),
+        "tar" => ("application/x-tar", Archive),
+        "gz" | "tgz" => ("application/gzip", Archive),
+        "bz2" => ("application/x-bzip2", Archive),
+        "xz" => ("application/x-xz", Archive),
+        "zst" => ("application/zstd", Archive),
+        "7z" => ("application/x-7z-compressed", Archive),
+        "rar" => ("application/vnd.rar", Archive),
+        "dmg" => ("application/x-apple-diskimage", Archive),
+        "jar" => ("application/java-archive", Archive),
+        "rs" => ("text/x-rust", Code),
+        "c" | "h" => ("text/x-c", Code),
+        "cpp" | "cc" | "hpp" => ("text/x-c++", Code),
+        "py" => ("text/x-python", Code),
+        "js" | "mjs" => ("text/javascript", Code),
+        "ts" | "tsx" => ("text/x-typescript", Code),
+        "go" => ("text/x-go", Code),
+        "java" => ("text/x-java", Code),
+        "swift" => ("text/x-swift", Code),
+        "rb" => ("text/x-ruby", Code),
+        "sh" | "bash" | "zsh" => ("text/x-shellscript", Code),
+        "html" | "htm" => ("text/html", Code),
+        "css" => ("text/css", Code),
+        "json" => ("application/json", Code),
+        "toml" => ("application/toml", Code),
+        "yaml" | "yml" => ("application/yaml", Code),
+        "xml" => ("application/xml", Code),
+        "sql" => ("application/sql", Code),
+        "exe" | "dll" => ("application/vnd.microsoft.portable-executable", Executable),
+        _ => return None,
+    };
+    Some(found)
+}
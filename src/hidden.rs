@@ -0,0 +1,115 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::notifications;
+use crate::settings::HiddenSettings;
+use crate::tag_db::FileEntry;
+
+/// `UF_HIDDEN` from `<sys/stat.h>`, set by `chflags hidden` on macOS.
+pub const UF_HIDDEN: u32 = 0x8000;
+
+/// How long a directory's `.hidden` listing is trusted before re-reading it.
+const LISTING_TTL: Duration = Duration::from_secs(2);
+
+struct Listing {
+    names: Arc<HashSet<OsString>>,
+    loaded: Instant,
+}
+
+/// Decides which entries count as hidden. Dot files are always hidden;
+/// backup files (`*~`), the platform hidden flag, names listed in a
+/// directory's `.hidden` file and user patterns are hidden per settings.
+pub struct HiddenPolicy {
+    patterns: GlobSet,
+    hide_backups: bool,
+    honor_hidden_files: bool,
+    listings: Mutex<HashMap<PathBuf, Listing>>,
+}
+
+impl HiddenPolicy {
+    pub fn new(settings: &HiddenSettings) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &settings.patterns {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => notifications::error!(format!("Invalid hide pattern {:?}: {}", pattern, e), None),
+            }
+        }
+        let patterns = builder.build().unwrap_or_else(|e| {
+            notifications::error!(format!("Could not build hide patterns: {}", e), None);
+            GlobSet::empty()
+        });
+
+        HiddenPolicy {
+            patterns,
+            hide_backups: settings.hide_backups,
+            honor_hidden_files: settings.honor_hidden_files,
+            listings: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_hidden(&self, entry: &FileEntry) -> bool {
+        let Some(name) = entry.path.file_name() else {
+            return false;
+        };
+
+        entry.name.starts_with('.')
+            || (self.hide_backups && entry.name.ends_with('~'))
+            || entry.metadata.flags & UF_HIDDEN != 0
+            || self.patterns.is_match(name)
+            || self.patterns.is_match(&entry.path)
+            || (self.honor_hidden_files
+                && entry
+                    .path
+                    .parent()
+                    .is_some_and(|dir| self.listed_names(dir).contains(name)))
+    }
+
+    /// Drops hidden entries from `files`.
+    pub fn retain_visible(&self, files: &mut Vec<FileEntry>) {
+        files.retain(|file| !self.is_hidden(file));
+    }
+
+    /// Names in `dir/.hidden`, one per line, as exact bytes.
+    fn listed_names(&self, dir: &Path) -> Arc<HashSet<OsString>> {
+        let mut listings = self.listings.lock().unwrap();
+        if let Some(listing) = listings.get(dir) {
+            if listing.loaded.elapsed() < LISTING_TTL {
+                return listing.names.clone();
+            }
+        }
+
+        let names: HashSet<OsString> = match std::fs::read(dir.join(".hidden")) {
+            Ok(content) => content
+                .split(|&byte| byte == b'\n')
+                .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+                .filter(|line| !line.is_empty())
+                .map(|line| OsString::from_vec(line.to_vec()))
+                .collect(),
+            // Folders inside an archive have no `.hidden` of their own.
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound | std::io::ErrorKind::NotADirectory) => {
+                HashSet::new()
+            }
+            Err(e) => {
+                notifications::warning!(format!("Could not read .hidden file: {}", e), Some(&dir.join(".hidden")));
+                HashSet::new()
+            }
+        };
+        let names = Arc::new(names);
+        listings.insert(
+            dir.to_path_buf(),
+            Listing {
+                names: names.clone(),
+                loaded: Instant::now(),
+            },
+        );
+        names
+    }
+}
@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+
+use crate::file_kind::FileKind;
+use crate::tabs::{Tab, ViewTab};
+
+const MAX_HISTORY: usize = 100;
+
+/// A snapshot of what a tab is showing, as recorded in the navigation history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub view: ViewTab,
+    pub folder_path: PathBuf,
+    pub tag: Option<String>,
+    pub kind: Option<FileKind>,
+    pub smart_folder: Option<String>,
+    pub search_query: String,
+}
+
+impl Location {
+    pub fn of(tab: &Tab) -> Self {
+        Location {
+            view: tab.view,
+            folder_path: tab.folder_current_path.clone(),
+            tag: tab.tag_selected.clone(),
+            kind: tab.kind_selected,
+            smart_folder: tab.smart_folder.clone(),
+            search_query: tab.search_query.clone(),
+        }
+    }
+
+    pub fn apply_to(&self, tab: &mut Tab) {
+        tab.view = self.view;
+        tab.folder_current_path = self.folder_path.clone();
+        tab.tag_selected = self.tag.clone();
+        tab.kind_selected = self.kind;
+        tab.smart_folder = self.smart_folder.clone();
+        tab.search_query = self.search_query.clone();
+        tab.last_search_query = self.search_query.clone();
+        tab.selected_file_index = None;
+    }
+
+    pub fn label(&self) -> String {
+        let base = match self.view {
+            ViewTab::Folders => self.folder_path.to_string_lossy().to_string(),
+            ViewTab::Tags => match (&self.tag, self.kind) {
+                (Some(tag), _) => format!("Tag: {}", tag),
+                (None, Some(kind)) => format!("Kind: {}", kind.label()),
+                (None, None) => "Tags".to_string(),
+            },
+            ViewTab::Recent => "Recent".to_string(),
+            ViewTab::Smart => match &self.smart_folder {
+                Some(name) => format!("Smart folder: {}", name),
+                None => "Smart folders".to_string(),
+            },
+        };
+
+        if self.search_query.is_empty() {
+            base
+        } else {
+            format!("{} — \"{}\"", base, self.search_query)
+        }
+    }
+
+    /// Two locations whose non-empty search queries extend one another are
+    /// the same query being typed or erased, so they share one history slot.
+    /// Any other change of query is a step of its own.
+    fn is_refinement_of(&self, other: &Location) -> bool {
+        self.view == other.view
+            && self.folder_path == other.folder_path
+            && self.tag == other.tag
+            && self.kind == other.kind
+            && self.smart_folder == other.smart_folder
+            && !self.search_query.is_empty()
+            && !other.search_query.is_empty()
+            && (self.search_query.starts_with(&other.search_query)
+                || other.search_query.starts_with(&self.search_query))
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    back: Vec<Location>,
+    forward: Vec<Location>,
+    current: Option<Location>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, location: Location) {
+        match self.current.take() {
+            Some(current) if current == location => {
+                self.current = Some(current);
+            }
+            Some(current) if location.is_refinement_of(&current) => {
+                self.current = Some(location);
+            }
+            Some(current) => {
+                self.back.push(current);
+                if self.back.len() > MAX_HISTORY {
+                    self.back.remove(0);
+                }
+                self.forward.clear();
+                self.current = Some(location);
+            }
+            None => {
+                self.current = Some(location);
+            }
+        }
+    }
+
+    /// Makes `location` current without adding a step, for changes that are
+    /// not navigation such as switching to another tab.
+    pub fn replace_current(&mut self, location: Location) {
+        self.current = Some(location);
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    /// Moves `offset` steps through the history (negative is back) and
+    /// returns the location that became current.
+    pub fn go(&mut self, offset: isize) -> Option<Location> {
+        let steps = offset.unsigned_abs();
+        let (from, to) = if offset < 0 {
+            (&mut self.back, &mut self.forward)
+        } else {
+            (&mut self.forward, &mut self.back)
+        };
+
+        if steps == 0 || steps > from.len() {
+            return None;
+        }
+
+        if let Some(current) = self.current.take() {
+            to.push(current);
+        }
+        for _ in 1..steps {
+            if let Some(location) = from.pop() {
+                to.push(location);
+            }
+        }
+        self.current = from.pop();
+        self.current.clone()
+    }
+
+    /// Most recent first.
+    pub fn back_entries(&self) -> impl Iterator<Item = &Location> {
+        self.back.iter().rev()
+    }
+
+    /// Nearest first.
+    pub fn forward_entries(&self) -> impl Iterator<Item = &Location> {
+        self.forward.iter().rev()
+    }
+
+    pub fn current(&self) -> Option<&Location> {
+        self.current.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(folder: &str, query: &str) -> Location {
+        Location {
+            view: ViewTab::Folders,
+            folder_path: PathBuf::from(folder),
+            tag: None,
+            kind: None,
+            smart_folder: None,
+            search_query: query.to_string(),
+        }
+    }
+
+    #[test]
+    fn back_and_forward_walk_the_recorded_locations() {
+        let mut history = History::new();
+        for folder in ["/a", "/b", "/c"] {
+            history.record(at(folder, ""));
+        }
+        history.record(at("/c", ""));
+
+        assert_eq!(history.go(-2), Some(at("/a", "")));
+        assert!(!history.can_go_back());
+        assert_eq!(history.go(1), Some(at("/b", "")));
+        assert_eq!(history.forward_entries().collect::<Vec<_>>(), [&at("/c", "")]);
+
+        history.record(at("/d", ""));
+        assert!(!history.can_go_forward());
+        assert_eq!(history.back_entries().collect::<Vec<_>>(), [&at("/b", ""), &at("/a", "")]);
+        assert_eq!(history.go(5), None);
+    }
+
+    #[test]
+    fn typing_a_query_shares_one_slot_but_a_new_query_does_not() {
+        let mut history = History::new();
+        history.record(at("/a", ""));
+        for query in ["f", "fo", "foo", "fo"] {
+            history.record(at("/a", query));
+        }
+        assert_eq!(history.back_entries().count(), 1);
+
+        history.record(at("/a", "bar"));
+        assert_eq!(history.go(-1), Some(at("/a", "fo")));
+    }
+
+    #[test]
+    fn switching_tabs_is_not_a_step() {
+        let mut history = History::new();
+        history.record(at("/a", ""));
+        history.record(at("/b", ""));
+        history.replace_current(at("/other-tab", ""));
+        assert_eq!(history.back_entries().collect::<Vec<_>>(), [&at("/a", "")]);
+
+        history.record(at("/c", ""));
+        assert_eq!(history.go(-1), Some(at("/other-tab", "")));
+    }
+}
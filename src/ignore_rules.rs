@@ -0,0 +1,88 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::notifications;
+
+/// Read in this order, so a later file overrides an earlier one in the same
+/// directory (the same precedence ripgrep uses).
+const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", ".fmsignore"];
+
+/// Hierarchical gitignore-style rules for the directory currently being
+/// walked: one matcher per ancestor that has ignore files, outermost first.
+/// Matchers are shared, so cloning the rules for a subdirectory is cheap.
+#[derive(Clone, Default)]
+pub struct IgnoreRules {
+    stack: Vec<(PathBuf, Arc<Gitignore>)>,
+}
+
+impl IgnoreRules {
+    /// Rules that apply inside `dir`, loaded from `dir` and all its ancestors.
+    pub fn for_directory(dir: &Path) -> Self {
+        let mut rules = IgnoreRules::default();
+        let mut ancestors: Vec<&Path> = dir.ancestors().collect();
+        ancestors.reverse();
+        for ancestor in ancestors {
+            rules.push_directory(ancestor);
+        }
+        rules
+    }
+
+    /// Drops rules from directories the walk has left, then adds the ignore
+    /// files found in `dir`.
+    pub fn enter(&mut self, dir: &Path) {
+        self.leave_to(dir);
+        self.push_directory(dir);
+    }
+
+    pub fn leave_to(&mut self, dir: &Path) {
+        while let Some((top, _)) = self.stack.last() {
+            if dir.starts_with(top) {
+                break;
+            }
+            self.stack.pop();
+        }
+    }
+
+    fn push_directory(&mut self, dir: &Path) {
+        if self.stack.last().is_some_and(|(top, _)| top == dir) {
+            return;
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut found = false;
+        for file_name in IGNORE_FILES {
+            let file = dir.join(file_name);
+            if file.is_file() {
+                found = true;
+                if let Some(e) = builder.add(&file) {
+                    notifications::warning!(format!("Could not read ignore file: {}", e), Some(&file));
+                }
+            }
+        }
+        if !found {
+            return;
+        }
+
+        match builder.build() {
+            Ok(gitignore) => self.stack.push((dir.to_path_buf(), Arc::new(gitignore))),
+            Err(e) => notifications::warning!(format!("Could not build ignore rules: {}", e), Some(dir)),
+        }
+    }
+
+    /// The innermost matching rule wins; a whitelist (`!pattern`) un-ignores.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for (dir, gitignore) in self.stack.iter().rev() {
+            if !path.starts_with(dir) || path == dir {
+                continue;
+            }
+            match gitignore.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
@@ -1,134 +1,569 @@
-use std::path::Path;
-use std::sync::Arc;
-use walkdir::WalkDir;
-use xattr;
-use std::time::SystemTime;
+use std::collections::{HashSet, VecDeque};
+use std::os::unix::fs::MetadataExt;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::tag_db::{TagDatabase, FileEntry, FileType};
+use globset::GlobSet;
+
+use crate::archive;
+use crate::ignore_rules::IgnoreRules;
+use crate::notifications;
+use crate::settings::IndexRoot;
+use crate::tag_db::{TagDatabase, Classified, DirectoryScan, FileType, IndexedFile, SyncStats};
+use crate::vfs::local::LocalVfs;
+
+/// Entries synced per transaction by the writer thread.
+const BATCH_SIZE: usize = 1000;
+const MAX_WALKERS: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndexStats {
+    /// Entries seen on disk.
+    pub entries: usize,
+    /// New or changed entries written to the index.
+    pub written: usize,
+    /// Stale entries pruned because they no longer exist.
+    pub removed: usize,
+    pub errors: usize,
+    pub elapsed: Duration,
+    pub cancelled: bool,
+}
+
+impl IndexStats {
+    pub fn files_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.entries as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    pub fn merge(&mut self, other: IndexStats) {
+        self.entries += other.entries;
+        self.written += other.written;
+        self.removed += other.removed;
+        self.errors += other.errors;
+        self.elapsed += other.elapsed;
+        self.cancelled |= other.cancelled;
+    }
+}
+
+/// Live counters for the indexing run in progress, updated by the walker
+/// threads and read by the UI.
+#[derive(Default)]
+struct IndexProgress {
+    roots_total: AtomicUsize,
+    roots_done: AtomicUsize,
+    dirs_found: AtomicUsize,
+    dirs_done: AtomicUsize,
+    entries: AtomicUsize,
+    errors: AtomicUsize,
+    current_path: Mutex<PathBuf>,
+    started: Mutex<Option<Instant>>,
+    cancelled: AtomicBool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProgressSnapshot {
+    pub roots_total: usize,
+    pub roots_done: usize,
+    pub dirs_found: usize,
+    pub dirs_done: usize,
+    pub entries: usize,
+    pub errors: usize,
+    pub current_path: PathBuf,
+    pub elapsed: Duration,
+    pub cancelled: bool,
+}
+
+impl ProgressSnapshot {
+    /// Time left for the directories discovered so far, extrapolated from
+    /// the average time spent per directory.
+    pub fn estimate_remaining(&self) -> Option<Duration> {
+        if self.dirs_done == 0 {
+            return None;
+        }
+        let remaining = self.dirs_found.saturating_sub(self.dirs_done) as u32;
+        Some(self.elapsed / self.dirs_done as u32 * remaining)
+    }
+}
+
+struct DirJob {
+    path: PathBuf,
+    depth: usize,
+    /// Rules in effect in the parent of `path`.
+    rules: IgnoreRules,
+}
+
+/// Directories waiting to be read, shared by the walker threads. `pending`
+/// counts queued plus in-progress jobs; the walk is done when it hits zero.
+struct WorkQueue {
+    state: Mutex<(VecDeque<DirJob>, usize)>,
+    changed: Condvar,
+}
+
+impl WorkQueue {
+    fn new(jobs: Vec<DirJob>) -> Self {
+        let pending = jobs.len();
+        WorkQueue {
+            state: Mutex::new((VecDeque::from(jobs), pending)),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn next(&self) -> Option<DirJob> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(job) = state.0.pop_front() {
+                return Some(job);
+            }
+            if state.1 == 0 {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Queues the subdirectories found by a job and marks that job finished.
+    fn finish(&self, children: Vec<DirJob>) {
+        let mut state = self.state.lock().unwrap();
+        state.1 += children.len();
+        state.0.extend(children);
+        state.1 -= 1;
+        self.changed.notify_all();
+    }
+}
+
+/// Per-walk settings shared by the walker threads.
+struct Walk<'a> {
+    root: &'a IndexRoot,
+    excludes: GlobSet,
+    root_device: Option<u64>,
+    /// Directories already walked, by (device, inode), so following symlinks
+    /// cannot loop.
+    visited: Mutex<HashSet<(u64, u64)>>,
+    errors: AtomicUsize,
+    progress: &'a IndexProgress,
+}
+
+impl Walk<'_> {
+    fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        self.progress.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
 
 pub struct FileIndexer {
     tag_db: Arc<TagDatabase>,
+    roots: Mutex<Vec<IndexRoot>>,
+    progress: IndexProgress,
+    local: LocalVfs,
 }
 
 impl FileIndexer {
     pub fn new(tag_db: Arc<TagDatabase>) -> Self {
-        FileIndexer { tag_db }
+        FileIndexer {
+            tag_db,
+            roots: Mutex::new(Vec::new()),
+            progress: IndexProgress::default(),
+            local: LocalVfs::new(),
+        }
+    }
+
+    /// Resets the progress counters for a run over `roots` roots.
+    pub fn begin_run(&self, roots: usize) {
+        let progress = &self.progress;
+        progress.roots_total.store(roots, Ordering::Relaxed);
+        for counter in [
+            &progress.roots_done,
+            &progress.dirs_found,
+            &progress.dirs_done,
+            &progress.entries,
+            &progress.errors,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        progress.current_path.lock().unwrap().clear();
+        *progress.started.lock().unwrap() = Some(Instant::now());
+        progress.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    pub fn progress(&self) -> ProgressSnapshot {
+        let progress = &self.progress;
+        ProgressSnapshot {
+            roots_total: progress.roots_total.load(Ordering::Relaxed),
+            roots_done: progress.roots_done.load(Ordering::Relaxed),
+            dirs_found: progress.dirs_found.load(Ordering::Relaxed),
+            dirs_done: progress.dirs_done.load(Ordering::Relaxed),
+            entries: progress.entries.load(Ordering::Relaxed),
+            errors: progress.errors.load(Ordering::Relaxed),
+            current_path: progress.current_path.lock().unwrap().clone(),
+            elapsed: progress
+                .started
+                .lock()
+                .unwrap()
+                .map(|started| started.elapsed())
+                .unwrap_or_default(),
+            cancelled: self.is_cancelled(),
+        }
+    }
+
+    /// Asks the walkers to stop; directories already queued are dropped
+    /// without being read.
+    pub fn cancel(&self) {
+        self.progress.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.progress.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Configured roots; shallow indexing uses the innermost root containing
+    /// a directory for its exclude patterns and ignore-file setting.
+    pub fn set_roots(&self, roots: Vec<IndexRoot>) {
+        *self.roots.lock().unwrap() = roots;
+    }
+
+    fn root_containing(&self, dir: &Path) -> Option<IndexRoot> {
+        self.roots
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|root| dir.starts_with(&root.path))
+            .max_by_key(|root| root.path.components().count())
+            .cloned()
     }
 
+    /// Re-reads one directory, filtered by the exclude patterns and ignore
+    /// files of its root exactly as a full walk would, so the two never
+    /// disagree about which entries belong in the index.
     pub fn index_directory_shallow(&self, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
         let entries = std::fs::read_dir(dir)?;
-        
+        let root = self.root_containing(dir);
+        let rules = if root.as_ref().is_none_or(|root| root.respect_ignore_files) {
+            IgnoreRules::for_directory(dir)
+        } else {
+            IgnoreRules::default()
+        };
+        let excludes = root.as_ref().map(IndexRoot::exclude_matcher);
+        let classified = self.tag_db.get_classifications(dir).unwrap_or_default();
+
+        let mut batch = Vec::new();
+        let mut complete = true;
         for entry in entries {
             match entry {
                 Ok(entry) => {
                     let path = entry.path();
-                    if let Err(e) = self.index_file(&path) {
-                        eprintln!("Error indexing {}: {}", path.display(), e);
+                    if excludes
+                        .as_ref()
+                        .is_some_and(|excludes| excludes.is_match(entry.file_name()) || excludes.is_match(&path))
+                    {
+                        continue;
+                    }
+                    match self.read_entry(&path, classified.get(&path)) {
+                        Ok((mut file, _)) => {
+                            file.entry.ignored = rules.is_ignored(&path, file.entry.file_type == FileType::Directory);
+                            batch.push(file);
+                        }
+                        Err(e) => {
+                            notifications::warning!(format!("Could not index: {}", e), Some(&path));
+                            complete = false;
+                        }
                     }
                 }
                 Err(e) => {
-                    eprintln!("Error reading directory entry: {}", e);
+                    notifications::warning!(format!("Could not read directory entry: {}", e), Some(dir));
+                    complete = false;
                 }
             }
         }
-        
+
+        self.tag_db.sync_directories(&[DirectoryScan {
+            dir: dir.to_path_buf(),
+            entries: batch,
+            complete,
+        }])?;
         Ok(())
     }
 
-    pub fn index_directory_with_depth(&self, root: &Path, max_depth: usize) -> Result<(), Box<dyn std::error::Error>> {
-        let walker = WalkDir::new(root)
-            .follow_links(false)
-            .max_depth(max_depth)
-            .into_iter();
+    /// Walks `root` with a pool of walker threads, each reading whole
+    /// directories and handing their entries to a single writer thread that
+    /// inserts them in batched transactions.
+    pub fn index_directory_with_depth(&self, root: &IndexRoot) -> Result<IndexStats, Box<dyn std::error::Error>> {
+        let started = Instant::now();
+        let rules = match (root.respect_ignore_files, root.path.parent()) {
+            (true, Some(parent)) => IgnoreRules::for_directory(parent),
+            _ => IgnoreRules::default(),
+        };
+        // The root itself is always followed, even when it is a symlink.
+        let (mut root_entry, resolved) = self.read_entry(&root.path, None)?;
+        let metadata = resolved.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "index root is a broken symlink")
+        })?;
+        let ignored = rules.is_ignored(&root.path, metadata.is_dir());
+        root_entry.entry.ignored = ignored;
 
-        for entry in walker {
-            match entry {
-                Ok(entry) => {
-                    let path = entry.path();
-                    if let Err(e) = self.index_file(path) {
-                        eprintln!("Error indexing {}: {}", path.display(), e);
+        let walk = Walk {
+            root,
+            excludes: root.exclude_matcher(),
+            root_device: root.same_filesystem.then(|| metadata.dev()),
+            visited: Mutex::new(HashSet::from([(metadata.dev(), metadata.ino())])),
+            errors: AtomicUsize::new(0),
+            progress: &self.progress,
+        };
+        self.progress.entries.fetch_add(1, Ordering::Relaxed);
+
+        let walkers = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(MAX_WALKERS);
+        let (sender, receiver) = mpsc::sync_channel::<DirectoryScan>(walkers * 4);
+        sender.send(DirectoryScan {
+            dir: root.path.parent().unwrap_or(&root.path).to_path_buf(),
+            entries: vec![root_entry],
+            complete: false,
+        })?;
+
+        let mut jobs = Vec::new();
+        if !ignored && metadata.is_dir() && root.max_depth != Some(0) {
+            jobs.push(DirJob {
+                path: root.path.clone(),
+                depth: 0,
+                rules,
+            });
+        }
+        self.progress.dirs_found.fetch_add(jobs.len(), Ordering::Relaxed);
+        let queue = WorkQueue::new(jobs);
+
+        let (entries, synced) = std::thread::scope(|scope| {
+            let writer = scope.spawn(|| self.write_batches(receiver, &walk.errors));
+            for _ in 0..walkers {
+                let sender = sender.clone();
+                let (queue, walk) = (&queue, &walk);
+                scope.spawn(move || {
+                    while let Some(job) = queue.next() {
+                        if self.is_cancelled() {
+                            queue.finish(Vec::new());
+                            continue;
+                        }
+                        // A panic must still finish the job, or the other
+                        // walkers would wait for it forever.
+                        let path = job.path.clone();
+                        let walked = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                            self.walk_directory(job, walk, &sender)
+                        }));
+                        let children = walked.unwrap_or_else(|_| {
+                            notifications::error!("Indexing this folder failed unexpectedly", Some(&path));
+                            walk.error();
+                            Vec::new()
+                        });
+                        queue.finish(children);
                     }
-                }
-                Err(e) => {
-                    eprintln!("Error walking directory: {}", e);
-                }
+                });
             }
-        }
+            drop(sender);
+            writer.join().unwrap_or_default()
+        });
+        self.progress.roots_done.fetch_add(1, Ordering::Relaxed);
 
-        Ok(())
+        Ok(IndexStats {
+            entries,
+            written: synced.written,
+            removed: synced.removed,
+            errors: walk.errors.load(Ordering::Relaxed),
+            elapsed: started.elapsed(),
+            cancelled: self.is_cancelled(),
+        })
     }
 
-    pub fn index_file(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        let metadata = std::fs::metadata(path)?;
-        let file_type = if metadata.is_dir() {
-            FileType::Directory
-        } else {
-            FileType::File
+    /// Reads one directory, sends its entries to the writer and returns the
+    /// subdirectories to walk next.
+    fn walk_directory(&self, job: DirJob, walk: &Walk, sender: &mpsc::SyncSender<DirectoryScan>) -> Vec<DirJob> {
+        let root = walk.root;
+        *self.progress.current_path.lock().unwrap() = job.path.clone();
+        let mut rules = job.rules;
+        if root.respect_ignore_files {
+            rules.enter(&job.path);
+        }
+
+        let read_dir = match std::fs::read_dir(&job.path) {
+            Ok(read_dir) => read_dir,
+            Err(e) => {
+                notifications::warning!(format!("Could not read folder: {}", e), Some(&job.path));
+                walk.error();
+                self.progress.dirs_done.fetch_add(1, Ordering::Relaxed);
+                return Vec::new();
+            }
         };
 
-        let name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("")
-            .to_string();
+        let classified = self.tag_db.get_classifications(&job.path).unwrap_or_default();
+        let depth = job.depth + 1;
+        let mut batch = Vec::new();
+        let mut children = Vec::new();
+        let mut complete = true;
+        for entry in read_dir {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    notifications::warning!(format!("Could not read folder: {}", e), Some(&job.path));
+                    walk.error();
+                    complete = false;
+                    continue;
+                }
+            };
+            let path = entry.path();
+            if walk.excludes.is_match(entry.file_name()) || walk.excludes.is_match(&path) {
+                continue;
+            }
 
-        let size = metadata.len();
-        let modified = match metadata.modified() {
-            Ok(time) => match time.duration_since(SystemTime::UNIX_EPOCH) {
-                Ok(duration) => duration.as_secs() as i64,
+            let previous = classified.get(&path);
+            let (mut file, resolved) = match self.read_entry(&path, previous) {
+                Ok(read) => read,
                 Err(e) => {
-                    eprintln!("SystemTime before UNIX_EPOCH for {}: {}", path.display(), e);
-                    0
+                    // The entry's existing row must survive, so the listing
+                    // cannot be used to prune.
+                    notifications::warning!(format!("Could not index: {}", e), Some(&path));
+                    walk.error();
+                    complete = false;
+                    continue;
+                }
+            };
+            let is_symlink = file.entry.file_type == FileType::Symlink;
+            let ignored = root.respect_ignore_files
+                && rules.is_ignored(&path, file.entry.file_type == FileType::Directory);
+            file.entry.ignored = ignored;
+            // An archive whose size and modification time are unchanged keeps
+            // the contents indexed last time instead of being decompressed
+            // again.
+            let unchanged = previous.is_some_and(|previous| {
+                previous.size == file.entry.size && previous.modified == file.entry.modified
+            });
+            if root.index_archives
+                && !ignored
+                && archive::is_archive(&file.entry)
+                && !(unchanged && self.tag_db.has_children(&path).unwrap_or(false))
+            {
+                match archive::directory_scans(&path) {
+                    Ok(scans) => {
+                        for scan in scans {
+                            self.progress.entries.fetch_add(scan.entries.len(), Ordering::Relaxed);
+                            if sender.send(scan).is_err() {
+                                return Vec::new();
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        notifications::warning!(format!("Could not index archive: {}", e), Some(&path));
+                        walk.error();
+                    }
                 }
-            },
-            Err(e) => {
-                eprintln!("Failed to read modification time for {}: {}", path.display(), e);
-                0
             }
-        };
+            batch.push(file);
 
-        let parent = path.parent().map(|p| p.to_path_buf());
+            // Ignored directories are kept as a single flagged entry rather
+            // than walked, so build output never floods the index.
+            let descend = resolved.is_some_and(|metadata| {
+                metadata.is_dir()
+                    && !ignored
+                    && (!is_symlink || root.follow_symlinks)
+                    && root.max_depth.is_none_or(|max| depth < max)
+                    && walk.root_device.is_none_or(|dev| dev == metadata.dev())
+                    && walk.visited.lock().unwrap().insert((metadata.dev(), metadata.ino()))
+            });
+            if descend {
+                children.push(DirJob {
+                    path,
+                    depth,
+                    rules: rules.clone(),
+                });
+            }
+        }
 
-        let file_entry = FileEntry {
-            path: path.to_path_buf(),
-            name,
-            file_type,
-            size,
-            modified,
-            parent,
+        self.progress.entries.fetch_add(batch.len(), Ordering::Relaxed);
+        self.progress.dirs_found.fetch_add(children.len(), Ordering::Relaxed);
+        self.progress.dirs_done.fetch_add(1, Ordering::Relaxed);
+        let scan = DirectoryScan {
+            dir: job.path,
+            entries: batch,
+            complete,
         };
+        if sender.send(scan).is_err() {
+            return Vec::new();
+        }
+        children
+    }
 
-        self.tag_db.insert_file(&file_entry)?;
+    /// Returns the number of entries received and what syncing them changed.
+    fn write_batches(&self, receiver: mpsc::Receiver<DirectoryScan>, errors: &AtomicUsize) -> (usize, SyncStats) {
+        let mut received = 0;
+        let mut synced = SyncStats::default();
+        let mut pending: Vec<DirectoryScan> = Vec::new();
+        let mut pending_entries = 0;
+        let mut flush = |pending: &mut Vec<DirectoryScan>, pending_entries: &mut usize| {
+            match self.tag_db.sync_directories(pending) {
+                Ok(stats) => {
+                    synced.written += stats.written;
+                    synced.unchanged += stats.unchanged;
+                    synced.removed += stats.removed;
+                }
+                Err(e) => {
+                    notifications::error!(format!("Could not write {} index entries: {}", pending_entries, e), None);
+                    errors.fetch_add(*pending_entries, Ordering::Relaxed);
+                    self.progress.errors.fetch_add(*pending_entries, Ordering::Relaxed);
+                }
+            }
+            pending.clear();
+            *pending_entries = 0;
+        };
 
-        if let Ok(tags) = self.get_macos_tags(path) {
-            let path_buf = path.to_path_buf();
-            for tag in tags {
-                self.tag_db.add_tag_to_file(&path_buf, &tag)?;
+        for scan in receiver {
+            received += scan.entries.len();
+            pending_entries += scan.entries.len();
+            pending.push(scan);
+            if pending_entries >= BATCH_SIZE {
+                flush(&mut pending, &mut pending_entries);
             }
         }
+        if !pending.is_empty() {
+            flush(&mut pending, &mut pending_entries);
+        }
+        (received, synced)
+    }
 
-        Ok(())
+    /// Reads `path` without following it, together with its macOS tags;
+    /// see `LocalVfs::read_entry_reusing`.
+    fn read_entry(
+        &self,
+        path: &Path,
+        previous: Option<&Classified>,
+    ) -> std::io::Result<(IndexedFile, Option<std::fs::Metadata>)> {
+        let (entry, resolved) = self.local.read_entry_reusing(path, previous)?;
+        let tags = self.get_macos_tags(path).unwrap_or_default();
+        Ok((IndexedFile { entry, tags }, resolved))
     }
 
     fn get_macos_tags(&self, path: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
         let xattr_key = "com.apple.metadata:_kMDItemUserTags";
-        
+
         if let Ok(Some(value)) = xattr::get(path, xattr_key) {
             use std::io::Cursor;
             let mut cursor = Cursor::new(&value);
-            if let Ok(plist_value) = plist::Value::from_reader(&mut cursor) {
-                if let plist::Value::Array(tags) = plist_value {
-                    return Ok(tags
-                        .iter()
-                        .filter_map(|v| {
-                            if let plist::Value::String(s) = v {
-                                Some(s.clone())
-                            } else {
-                                None
-                            }
-                        })
-                        .collect());
-                }
+            if let Ok(plist::Value::Array(tags)) = plist::Value::from_reader(&mut cursor) {
+                return Ok(tags
+                    .iter()
+                    .filter_map(|v| {
+                        if let plist::Value::String(s) = v {
+                            Some(s.clone())
+                        } else {
+                            None
+                        }
+                    })
+                    .collect());
             }
         }
 
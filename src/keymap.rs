@@ -0,0 +1,647 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::notifications;
+
+/// How long the first chord of a sequence such as `g g` waits for the next.
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Where an action applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    Global,
+    /// Only in the Folders view.
+    Folders,
+    /// The file list of whichever view is shown.
+    List,
+}
+
+impl Context {
+    pub fn label(self) -> &'static str {
+        match self {
+            Context::Global => "Global",
+            Context::Folders => "Folders view",
+            Context::List => "File list",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    Back,
+    Forward,
+    FocusSearch,
+    ShowFolders,
+    ShowTags,
+    ShowRecent,
+    ShowSmart,
+    ToggleHidden,
+    OpenSettings,
+    ToggleTree,
+    CollapseTree,
+    NewFile,
+    NewFolder,
+    ToggleDirectorySearch,
+    SelectNext,
+    SelectPrevious,
+    SelectFirst,
+    SelectLast,
+    OpenSelected,
+    GoToParent,
+    CommandPalette,
+    ShowLog,
+    ToggleMark,
+    BatchRename,
+    ShowProperties,
+    /// Opens the favorite at this zero-based position.
+    OpenFavorite(usize),
+}
+
+impl Action {
+    const FIXED: [Action; 29] = [
+        Action::NewTab,
+        Action::CloseTab,
+        Action::NextTab,
+        Action::PreviousTab,
+        Action::Back,
+        Action::Forward,
+        Action::FocusSearch,
+        Action::ShowFolders,
+        Action::ShowTags,
+        Action::ShowRecent,
+        Action::ShowSmart,
+        Action::ToggleHidden,
+        Action::OpenSettings,
+        Action::ToggleTree,
+        Action::CollapseTree,
+        Action::NewFile,
+        Action::NewFolder,
+        Action::ToggleDirectorySearch,
+        Action::SelectNext,
+        Action::SelectPrevious,
+        Action::SelectFirst,
+        Action::SelectLast,
+        Action::OpenSelected,
+        Action::GoToParent,
+        Action::CommandPalette,
+        Action::ShowLog,
+        Action::ToggleMark,
+        Action::BatchRename,
+        Action::ShowProperties,
+    ];
+
+    pub const FAVORITE_SLOTS: usize = 9;
+
+    /// Every bindable action, favorites included.
+    pub fn all() -> Vec<Action> {
+        Action::FIXED
+            .into_iter()
+            .chain((0..Action::FAVORITE_SLOTS).map(Action::OpenFavorite))
+            .collect()
+    }
+
+    /// The name used in `keymap.json`, e.g. `new_folder` or `open_favorite_3`.
+    pub fn name(self) -> String {
+        let name = match self {
+            Action::NewTab => "new_tab",
+            Action::CloseTab => "close_tab",
+            Action::NextTab => "next_tab",
+            Action::PreviousTab => "previous_tab",
+            Action::Back => "back",
+            Action::Forward => "forward",
+            Action::FocusSearch => "focus_search",
+            Action::ShowFolders => "show_folders",
+            Action::ShowTags => "show_tags",
+            Action::ShowRecent => "show_recent",
+            Action::ShowSmart => "show_smart",
+            Action::ToggleHidden => "toggle_hidden",
+            Action::OpenSettings => "open_settings",
+            Action::ToggleTree => "toggle_tree",
+            Action::CollapseTree => "collapse_tree",
+            Action::NewFile => "new_file",
+            Action::NewFolder => "new_folder",
+            Action::ToggleDirectorySearch => "toggle_directory_search",
+            Action::SelectNext => "select_next",
+            Action::SelectPrevious => "select_previous",
+            Action::SelectFirst => "select_first",
+            Action::SelectLast => "select_last",
+            Action::OpenSelected => "open_selected",
+            Action::GoToParent => "go_to_parent",
+            Action::CommandPalette => "command_palette",
+            Action::ShowLog => "show_log",
+            Action::ToggleMark => "toggle_mark",
+            Action::BatchRename => "batch_rename",
+            Action::ShowProperties => "show_properties",
+            Action::OpenFavorite(index) => return format!("open_favorite_{}", index + 1),
+        };
+        name.to_string()
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        if let Some(number) = name.strip_prefix("open_favorite_") {
+            return match number.parse::<usize>() {
+                Ok(n @ 1..=Action::FAVORITE_SLOTS) => Some(Action::OpenFavorite(n - 1)),
+                _ => None,
+            };
+        }
+        Action::FIXED.into_iter().find(|action| action.name() == name)
+    }
+
+    pub fn label(self) -> String {
+        let label = match self {
+            Action::NewTab => "New tab",
+            Action::CloseTab => "Close tab",
+            Action::NextTab => "Next tab",
+            Action::PreviousTab => "Previous tab",
+            Action::Back => "Go back",
+            Action::Forward => "Go forward",
+            Action::FocusSearch => "Focus search",
+            Action::ShowFolders => "Show Folders view",
+            Action::ShowTags => "Show Tags view",
+            Action::ShowRecent => "Show Recent view",
+            Action::ShowSmart => "Show Smart folders view",
+            Action::ToggleHidden => "Toggle hidden files",
+            Action::OpenSettings => "Open settings",
+            Action::ToggleTree => "Toggle directory tree",
+            Action::CollapseTree => "Collapse directory tree",
+            Action::NewFile => "New file",
+            Action::NewFolder => "New folder",
+            Action::ToggleDirectorySearch => "Toggle search in current directory",
+            Action::SelectNext => "Select next",
+            Action::SelectPrevious => "Select previous",
+            Action::SelectFirst => "Select first",
+            Action::SelectLast => "Select last",
+            Action::OpenSelected => "Open selected",
+            Action::GoToParent => "Go to parent folder",
+            Action::CommandPalette => "Show command palette",
+            Action::ShowLog => "Show log",
+            Action::ToggleMark => "Mark or unmark selected",
+            Action::BatchRename => "Rename marked or selected",
+            Action::ShowProperties => "Show properties of selected",
+            Action::OpenFavorite(index) => return format!("Open favorite {}", index + 1),
+        };
+        label.to_string()
+    }
+
+    pub fn context(self) -> Context {
+        match self {
+            Action::CollapseTree
+            | Action::NewFile
+            | Action::NewFolder
+            | Action::ToggleDirectorySearch
+            | Action::GoToParent => Context::Folders,
+            Action::SelectNext
+            | Action::SelectPrevious
+            | Action::SelectFirst
+            | Action::SelectLast
+            | Action::OpenSelected
+            | Action::ToggleMark
+            | Action::BatchRename
+            | Action::ShowProperties => Context::List,
+            _ => Context::Global,
+        }
+    }
+}
+
+/// One key press with its modifiers. `command` stands for Cmd on macOS and
+/// Ctrl elsewhere; either key satisfies it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    pub command: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub key: egui::Key,
+}
+
+impl Chord {
+    /// With Shift held a digit arrives as the symbol it types (`!` for
+    /// Shift+1 on most layouts), so digits are taken from the physical key.
+    fn from_event(key: egui::Key, physical_key: Option<egui::Key>, modifiers: egui::Modifiers) -> Self {
+        use egui::Key::*;
+        let key = match physical_key {
+            Some(digit @ (Num0 | Num1 | Num2 | Num3 | Num4 | Num5 | Num6 | Num7 | Num8 | Num9)) if modifiers.shift => digit,
+            _ => key,
+        };
+        Chord {
+            command: modifiers.command || modifiers.ctrl,
+            alt: modifiers.alt,
+            shift: modifiers.shift,
+            key,
+        }
+    }
+
+    /// Parses `Cmd+Shift+P`, `Alt+Left`, `/` and the like. `Ctrl`, `Cmd`,
+    /// `Command` and `Control` are the same modifier.
+    fn parse(text: &str) -> Result<Self, String> {
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None if text == "+" => ("", "+"),
+            None => match text.rsplit_once('+') {
+                Some((modifiers, key)) => (modifiers, key),
+                None => ("", text),
+            },
+        };
+
+        let mut chord = Chord {
+            command: false,
+            alt: false,
+            shift: false,
+            key: egui::Key::from_name(key).ok_or_else(|| format!("unknown key {:?}", key))?,
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "cmd" | "command" | "ctrl" | "control" => chord.command = true,
+                "alt" | "option" | "opt" => chord.alt = true,
+                "shift" => chord.shift = true,
+                other => return Err(format!("unknown modifier {:?}", other)),
+            }
+        }
+        Ok(chord)
+    }
+
+    /// Keys a focused text field uses for typing or editing: plain keys
+    /// other than list navigation, and Alt with the word-editing keys.
+    fn types_into_text(&self) -> bool {
+        use egui::Key::*;
+        if self.command {
+            false
+        } else if self.alt {
+            matches!(self.key, ArrowLeft | ArrowRight | Backspace | Delete)
+        } else {
+            !matches!(
+                self.key,
+                ArrowUp | ArrowDown | Enter | Escape | Tab | PageUp | PageDown
+                    | F1 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | F10 | F11 | F12
+            )
+        }
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.command {
+            write!(f, "Cmd+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{}", self.key.symbol_or_name())
+    }
+}
+
+/// A chord or a sequence of chords, written space-separated (`g g`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySequence(Vec<Chord>);
+
+impl KeySequence {
+    fn parse(text: &str) -> Result<Self, String> {
+        let chords = text
+            .split_whitespace()
+            .map(Chord::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if chords.is_empty() {
+            return Err("empty key binding".to_string());
+        }
+        Ok(KeySequence(chords))
+    }
+
+    fn starts_with(&self, prefix: &[Chord]) -> bool {
+        self.0.starts_with(prefix)
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, chord) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", chord)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeymapPreset {
+    #[default]
+    Default,
+    /// The default bindings plus j/k/h/l, `g g`/`G` and `/` for search.
+    Vim,
+}
+
+impl KeymapPreset {
+    pub const ALL: [KeymapPreset; 2] = [KeymapPreset::Default, KeymapPreset::Vim];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeymapPreset::Default => "Default",
+            KeymapPreset::Vim => "Vim-like",
+        }
+    }
+
+    fn bindings(self) -> Vec<(&'static str, Action)> {
+        let mut bindings = vec![
+            ("Cmd+T", Action::NewTab),
+            ("Cmd+W", Action::CloseTab),
+            ("Ctrl+Tab", Action::NextTab),
+            ("Ctrl+Shift+Tab", Action::PreviousTab),
+            ("Alt+Left", Action::Back),
+            ("Alt+Right", Action::Forward),
+            ("Cmd+F", Action::FocusSearch),
+            ("Cmd+1", Action::ShowFolders),
+            ("Cmd+2", Action::ShowTags),
+            ("Cmd+3", Action::ShowRecent),
+            ("Cmd+4", Action::ShowSmart),
+            ("Cmd+.", Action::ToggleHidden),
+            ("Cmd+,", Action::OpenSettings),
+            ("Cmd+B", Action::ToggleTree),
+            ("Cmd+K", Action::CollapseTree),
+            ("Cmd+Shift+.", Action::CollapseTree),
+            ("Cmd+N", Action::NewFile),
+            ("Cmd+D", Action::NewFolder),
+            ("Cmd+Alt+F", Action::ToggleDirectorySearch),
+            ("Down", Action::SelectNext),
+            ("Up", Action::SelectPrevious),
+            ("Home", Action::SelectFirst),
+            ("End", Action::SelectLast),
+            ("Enter", Action::OpenSelected),
+            ("Left", Action::GoToParent),
+            ("Backspace", Action::GoToParent),
+            ("Cmd+Shift+P", Action::CommandPalette),
+            ("Cmd+Shift+L", Action::ShowLog),
+            ("Space", Action::ToggleMark),
+            ("F2", Action::BatchRename),
+            ("Alt+Enter", Action::ShowProperties),
+        ];
+        const FAVORITE_CHORDS: [&str; Action::FAVORITE_SLOTS] = [
+            "Cmd+Shift+1", "Cmd+Shift+2", "Cmd+Shift+3", "Cmd+Shift+4", "Cmd+Shift+5",
+            "Cmd+Shift+6", "Cmd+Shift+7", "Cmd+Shift+8", "Cmd+Shift+9",
+        ];
+        bindings.extend(FAVORITE_CHORDS.into_iter().zip((0..).map(Action::OpenFavorite)));
+
+        if self == KeymapPreset::Vim {
+            bindings.extend([
+                ("J", Action::SelectNext),
+                ("K", Action::SelectPrevious),
+                ("H", Action::GoToParent),
+                ("L", Action::OpenSelected),
+                ("G G", Action::SelectFirst),
+                ("Shift+G", Action::SelectLast),
+                ("/", Action::FocusSearch),
+            ]);
+        }
+        bindings
+    }
+}
+
+/// `~/.fms/keymap.json`: a preset plus overrides. Mapping a chord to `null`
+/// removes it from the preset.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct KeymapFile {
+    preset: KeymapPreset,
+    bindings: BTreeMap<String, Option<String>>,
+}
+
+/// Resolves key presses to actions using the preset and the user's keymap
+/// file. Problems found while loading, including conflicting bindings, are
+/// kept in `problems` for the settings window.
+pub struct Keymap {
+    file: KeymapFile,
+    bindings: Vec<(KeySequence, Action)>,
+    problems: Vec<String>,
+    pending: Vec<Chord>,
+    pending_since: Option<Instant>,
+    config_path: PathBuf,
+}
+
+impl Keymap {
+    pub fn new(config_dir: &Path) -> Self {
+        let config_path = config_dir.join("keymap.json");
+        let file = Self::load_config(&config_path);
+        let mut keymap = Keymap {
+            file,
+            bindings: Vec::new(),
+            problems: Vec::new(),
+            pending: Vec::new(),
+            pending_since: None,
+            config_path,
+        };
+        keymap.rebuild();
+        keymap
+    }
+
+    fn load_config(config_path: &Path) -> KeymapFile {
+        if !config_path.exists() {
+            return KeymapFile::default();
+        }
+
+        match std::fs::read_to_string(config_path) {
+            Ok(content) => match serde_json::from_str::<KeymapFile>(&content) {
+                Ok(file) => file,
+                Err(e) => {
+                    notifications::error!(format!("Could not parse keymap: {}", e), Some(config_path));
+                    KeymapFile::default()
+                }
+            },
+            Err(e) => {
+                notifications::error!(format!("Could not read keymap: {}", e), Some(config_path));
+                KeymapFile::default()
+            }
+        }
+    }
+
+    fn save(&self) {
+        let result = serde_json::to_string_pretty(&self.file)
+            .map_err(std::io::Error::from)
+            .and_then(|json| {
+                if let Some(parent) = self.config_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&self.config_path, json)
+            });
+        if let Err(e) = result {
+            notifications::error!(format!("Could not save keymap: {}", e), Some(&self.config_path));
+        }
+    }
+
+    pub fn preset(&self) -> KeymapPreset {
+        self.file.preset
+    }
+
+    pub fn set_preset(&mut self, preset: KeymapPreset) {
+        if preset != self.file.preset {
+            self.file.preset = preset;
+            self.rebuild();
+            self.save();
+        }
+    }
+
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+
+    /// The bindings of `action`, formatted for display.
+    pub fn bindings_for(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(sequence, _)| sequence.to_string())
+            .collect()
+    }
+
+    /// Applies the user's overrides to the preset and checks the result:
+    /// two bindings conflict when one sequence equals or begins the other.
+    fn rebuild(&mut self) {
+        let mut problems = Vec::new();
+        let mut bindings: Vec<(KeySequence, Action)> = self
+            .file
+            .preset
+            .bindings()
+            .into_iter()
+            .map(|(text, action)| (KeySequence::parse(text).expect("preset binding"), action))
+            .collect();
+
+        let mut overrides = Vec::new();
+        for (text, action) in &self.file.bindings {
+            let sequence = match KeySequence::parse(text) {
+                Ok(sequence) => sequence,
+                Err(e) => {
+                    problems.push(format!("\"{}\": {}", text, e));
+                    continue;
+                }
+            };
+            let action = match action.as_deref().map(|name| (name, Action::from_name(name))) {
+                None => None,
+                Some((_, Some(action))) => Some(action),
+                Some((name, None)) => {
+                    problems.push(format!("\"{}\": unknown action {:?}", text, name));
+                    continue;
+                }
+            };
+            bindings.retain(|(bound, _)| *bound != sequence);
+            overrides.push((sequence, action));
+        }
+        for (sequence, action) in overrides {
+            if let Some(action) = action {
+                if let Some((_, previous)) = bindings.iter().find(|(bound, _)| *bound == sequence) {
+                    problems.push(format!(
+                        "{} is bound to both {} and {}",
+                        sequence,
+                        previous.name(),
+                        action.name()
+                    ));
+                    continue;
+                }
+                bindings.push((sequence, action));
+            }
+        }
+
+        for (i, (first, first_action)) in bindings.iter().enumerate() {
+            for (second, second_action) in &bindings[i + 1..] {
+                let ((shorter, shorter_action), (longer, longer_action)) = if first.0.len() <= second.0.len() {
+                    ((first, first_action), (second, second_action))
+                } else {
+                    ((second, second_action), (first, first_action))
+                };
+                if shorter.0.len() < longer.0.len() && longer.starts_with(&shorter.0) {
+                    problems.push(format!(
+                        "{} ({}) hides {} ({})",
+                        shorter,
+                        shorter_action.name(),
+                        longer,
+                        longer_action.name()
+                    ));
+                }
+            }
+        }
+
+        for problem in &problems {
+            notifications::warning!(format!("Keymap: {}", problem), Some(&self.config_path));
+        }
+        self.bindings = bindings;
+        self.problems = problems;
+        self.pending.clear();
+        self.pending_since = None;
+    }
+
+    /// Turns this frame's key presses into actions. While a text field has
+    /// focus, plain keys are left to it.
+    pub fn process(&mut self, input: &egui::InputState, text_focused: bool) -> Vec<Action> {
+        let mut actions = Vec::new();
+        if self.pending_since.is_some_and(|since| since.elapsed() > SEQUENCE_TIMEOUT) {
+            self.pending.clear();
+            self.pending_since = None;
+        }
+
+        for event in &input.events {
+            let egui::Event::Key { key, physical_key, pressed: true, modifiers, .. } = event else {
+                continue;
+            };
+            let chord = Chord::from_event(*key, *physical_key, *modifiers);
+            if text_focused && chord.types_into_text() {
+                self.pending.clear();
+                continue;
+            }
+
+            self.pending.push(chord);
+            if !self.bindings.iter().any(|(sequence, _)| sequence.starts_with(&self.pending)) {
+                // Not a continuation; the key may still start a sequence of its own.
+                self.pending = vec![chord];
+            }
+
+            if let Some((_, action)) = self.bindings.iter().find(|(sequence, _)| sequence.0 == self.pending) {
+                actions.push(*action);
+                self.pending.clear();
+            } else if self.bindings.iter().any(|(sequence, _)| sequence.starts_with(&self.pending)) {
+                self.pending_since = Some(Instant::now());
+            } else {
+                self.pending.clear();
+            }
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: egui::Key, physical_key: egui::Key, modifiers: egui::Modifiers) -> egui::InputState {
+        let mut input = egui::InputState::default();
+        input.events.push(egui::Event::Key {
+            key,
+            physical_key: Some(physical_key),
+            pressed: true,
+            repeat: false,
+            modifiers,
+        });
+        input
+    }
+
+    #[test]
+    fn shifted_digits_open_favorites_whatever_symbol_they_type() {
+        let mut keymap = Keymap::new(Path::new("/nonexistent"));
+        let modifiers = egui::Modifiers::COMMAND | egui::Modifiers::SHIFT;
+
+        // Shift+1 types `+` on a Swiss keyboard.
+        let input = press(egui::Key::Plus, egui::Key::Num1, modifiers);
+        assert_eq!(keymap.process(&input, false), [Action::OpenFavorite(0)]);
+
+        let input = press(egui::Key::Num9, egui::Key::Num9, modifiers);
+        assert_eq!(keymap.process(&input, false), [Action::OpenFavorite(8)]);
+    }
+}
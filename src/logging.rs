@@ -0,0 +1,283 @@
+//! The `log` backend: records at or above the configured level for their
+//! module go to `~/.fms/logs/fms.log`, which is rotated by size, and to an
+//! in-memory buffer for the log viewer. Levels come from `settings.json` and
+//! can be overridden with `FMS_LOG`, e.g. `FMS_LOG=warn,fms::indexer=debug`.
+
+use chrono::{DateTime, Local};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::settings::LoggingSettings;
+
+pub const LOG_ENV_VAR: &str = "FMS_LOG";
+const LOG_FILE_NAME: &str = "fms.log";
+const RECENT_LIMIT: usize = 2000;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// A default level plus per-target overrides; the longest matching target
+/// prefix wins.
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    default: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter {
+            default: LevelFilter::Info,
+            targets: Vec::new(),
+        }
+    }
+}
+
+impl Filter {
+    /// Parses `level` or `target=level` items separated by commas.
+    fn parse(spec: &str) -> Result<Filter, String> {
+        let mut filter = Filter::default();
+        for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            match item.split_once('=') {
+                Some((target, level)) => filter.targets.push((target.trim().to_string(), parse_level(level)?)),
+                None => filter.default = parse_level(item)?,
+            }
+        }
+        Ok(filter)
+    }
+
+    fn from_settings(settings: &LoggingSettings) -> Result<Filter, String> {
+        let mut filter = Filter {
+            default: parse_level(&settings.level)?,
+            targets: Vec::new(),
+        };
+        for (target, level) in &settings.targets {
+            filter.targets.push((target.clone(), parse_level(level)?));
+        }
+        Ok(filter)
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| {
+                target == prefix || target.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+}
+
+fn parse_level(text: &str) -> Result<LevelFilter, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("unknown log level {:?}", text.trim()))
+}
+
+/// The open log file and how far it has grown.
+struct LogFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(dir: &Path) -> std::io::Result<LogFile> {
+        std::fs::create_dir_all(dir)?;
+        let file = OpenOptions::new().create(true).append(true).open(dir.join(LOG_FILE_NAME))?;
+        let size = file.metadata()?.len();
+        Ok(LogFile {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+        })
+    }
+
+    /// Shifts `fms.log.N` to `fms.log.N+1`, dropping the oldest, and starts
+    /// a fresh `fms.log`.
+    fn rotate(&mut self, max_files: usize) -> std::io::Result<()> {
+        let numbered = |index: usize| self.dir.join(format!("{}.{}", LOG_FILE_NAME, index));
+        if max_files == 0 {
+            self.file.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+        let _ = std::fs::remove_file(numbered(max_files));
+        for index in (1..max_files).rev() {
+            let _ = std::fs::rename(numbered(index), numbered(index + 1));
+        }
+        std::fs::rename(self.dir.join(LOG_FILE_NAME), numbered(1))?;
+        *self = LogFile::open(&self.dir)?;
+        Ok(())
+    }
+}
+
+struct State {
+    filter: Filter,
+    /// Set when `FMS_LOG` is present; the config file is then ignored.
+    env_override: bool,
+    file: Option<LogFile>,
+    max_file_size: u64,
+    max_files: usize,
+    recent: VecDeque<LogLine>,
+}
+
+struct Logger {
+    state: Mutex<State>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let state = self.state.lock().unwrap();
+        metadata.level() <= state.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        let mut state = self.state.lock().unwrap();
+        if record.level() > state.filter.level_for(record.target()) {
+            return;
+        }
+
+        let line = LogLine {
+            time: Local::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        let text = format!(
+            "{} {:<5} {}: {}\n",
+            line.time.format("%Y-%m-%dT%H:%M:%S%.3f"),
+            line.level,
+            line.target,
+            line.message
+        );
+
+        let (max_file_size, max_files) = (state.max_file_size, state.max_files);
+        if let Some(log_file) = &mut state.file {
+            if log_file.size > 0 && log_file.size + text.len() as u64 > max_file_size {
+                if let Err(e) = log_file.rotate(max_files) {
+                    eprintln!("Error rotating log file: {}", e);
+                }
+            }
+            match log_file.file.write_all(text.as_bytes()) {
+                Ok(()) => log_file.size += text.len() as u64,
+                Err(e) => eprintln!("Error writing log file: {}", e),
+            }
+        }
+
+        state.recent.push_back(line);
+        if state.recent.len() > RECENT_LIMIT {
+            state.recent.pop_front();
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(log_file) = &mut self.state.lock().unwrap().file {
+            let _ = log_file.file.flush();
+        }
+    }
+}
+
+/// Installs the logger, writing to `log_dir`. Until `configure` runs, only
+/// `FMS_LOG` (or the default, info) decides what is recorded.
+pub fn init(log_dir: &Path) {
+    let (filter, env_override) = match std::env::var(LOG_ENV_VAR) {
+        Ok(spec) => match Filter::parse(&spec) {
+            Ok(filter) => (filter, true),
+            Err(e) => {
+                eprintln!("Ignoring {}: {}", LOG_ENV_VAR, e);
+                (Filter::default(), false)
+            }
+        },
+        Err(_) => (Filter::default(), false),
+    };
+    let file = match LogFile::open(log_dir) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            eprintln!("Error opening log file in {}: {}", log_dir.display(), e);
+            None
+        }
+    };
+    let defaults = LoggingSettings::default();
+    let logger = LOGGER.get_or_init(|| Logger {
+        state: Mutex::new(State {
+            filter,
+            env_override,
+            file,
+            max_file_size: defaults.max_file_size_kb * 1024,
+            max_files: defaults.max_files,
+            recent: VecDeque::new(),
+        }),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+}
+
+/// Applies the `logging` section of `settings.json`.
+pub fn configure(settings: &LoggingSettings) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let filter = Filter::from_settings(settings);
+    let mut state = logger.state.lock().unwrap();
+    state.max_file_size = settings.max_file_size_kb.max(1) * 1024;
+    state.max_files = settings.max_files;
+    if state.env_override {
+        return;
+    }
+    match filter {
+        Ok(filter) => state.filter = filter,
+        Err(e) => {
+            drop(state);
+            log::warn!("Ignoring logging settings: {}", e);
+        }
+    }
+}
+
+/// The most recent records, oldest first.
+pub fn recent() -> Vec<LogLine> {
+    LOGGER
+        .get()
+        .map(|logger| logger.state.lock().unwrap().recent.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// The file currently written to, if it could be opened.
+pub fn log_file_path() -> Option<PathBuf> {
+    let logger = LOGGER.get()?;
+    let state = logger.state.lock().unwrap();
+    state.file.as_ref().map(|file| file.dir.join(LOG_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_matching_target_wins() {
+        let filter = Filter::parse("warn, fms=info, fms::indexer=trace").unwrap();
+        assert_eq!(filter.level_for("eframe"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("fms"), LevelFilter::Info);
+        assert_eq!(filter.level_for("fms::app"), LevelFilter::Info);
+        assert_eq!(filter.level_for("fms::indexer"), LevelFilter::Trace);
+        // A prefix only matches whole path segments.
+        assert_eq!(filter.level_for("fmsx"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn unknown_levels_are_rejected() {
+        assert!(Filter::parse("fms=loud").is_err());
+    }
+}
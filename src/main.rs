@@ -1,23 +1,51 @@
 mod app;
+mod archive;
+mod batch_rename;
+mod bookmarks;
 mod file_associations;
+mod file_kind;
+mod hidden;
+mod history;
+mod ignore_rules;
 mod indexer;
+mod keymap;
+mod logging;
+mod notifications;
+mod os_path;
+mod properties;
 mod search;
+mod session;
+mod settings;
+mod smart_folders;
+mod tabs;
 mod tag_db;
 mod ui;
+mod vfs;
 
 use app::FileManagerApp;
+use std::path::PathBuf;
 
 fn main() -> eframe::Result<()> {
+    let home_dir = settings::home_dir().unwrap_or_else(|| PathBuf::from("/"));
+    logging::init(&home_dir.join(".fms").join("logs"));
+    log::info!("Starting fms {}", env!("CARGO_PKG_VERSION"));
+
+    let smart_folder = std::env::args()
+        .skip_while(|arg| arg != "--smart")
+        .nth(1);
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([600.0, 600.0])
             .with_title("FMS - Find My Shiet"),
+        follow_system_theme: true,
+        persist_window: true,
         ..Default::default()
     };
 
     eframe::run_native(
         "FMS",
         options,
-        Box::new(|_cc| Box::new(FileManagerApp::new())),
+        Box::new(move |cc| Box::new(FileManagerApp::new(cc, smart_folder))),
     )
 }
@@ -0,0 +1,190 @@
+//! Errors and notices meant for the user. Any module, on any thread, reports
+//! through `error!`, `warning!` or `info!`; every report is logged, and once
+//! the app has installed the receiving end it is also drained every frame
+//! into toasts and the notifications panel.
+
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+const HISTORY_LIMIT: usize = 500;
+const TOAST_LIMIT: usize = 4;
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+static SENDER: OnceLock<mpsc::Sender<Notification>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "Info",
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ",
+            Severity::Warning => "⚠",
+            Severity::Error => "⛔",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    /// The file or folder the notice is about, if any.
+    pub path: Option<PathBuf>,
+    pub time: DateTime<Local>,
+}
+
+impl From<Severity> for log::Level {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Info => log::Level::Info,
+            Severity::Warning => log::Level::Warn,
+            Severity::Error => log::Level::Error,
+        }
+    }
+}
+
+/// Logs the notice under `target` and passes it on to the app. Use the
+/// `error!`, `warning!` and `info!` macros, which fill in the caller's module.
+pub fn report(target: &str, severity: Severity, message: String, path: Option<&Path>) {
+    match path {
+        Some(path) => log::log!(target: target, severity.into(), "{} ({})", message, path.display()),
+        None => log::log!(target: target, severity.into(), "{}", message),
+    }
+
+    if let Some(sender) = SENDER.get() {
+        let _ = sender.send(Notification {
+            severity,
+            message,
+            path: path.map(Path::to_path_buf),
+            time: Local::now(),
+        });
+    }
+}
+
+/// Something failed that the user asked for or relies on; shown as a toast.
+macro_rules! error {
+    ($message:expr, $path:expr) => {
+        $crate::notifications::report(
+            module_path!(),
+            $crate::notifications::Severity::Error,
+            $message.into(),
+            $path,
+        )
+    };
+}
+
+/// A problem worth keeping in the history, such as an unreadable folder
+/// during indexing, that would be too noisy as a toast.
+macro_rules! warning {
+    ($message:expr, $path:expr) => {
+        $crate::notifications::report(
+            module_path!(),
+            $crate::notifications::Severity::Warning,
+            $message.into(),
+            $path,
+        )
+    };
+}
+
+macro_rules! info {
+    ($message:expr, $path:expr) => {
+        $crate::notifications::report(
+            module_path!(),
+            $crate::notifications::Severity::Info,
+            $message.into(),
+            $path,
+        )
+    };
+}
+
+pub(crate) use {error, info, warning};
+
+/// The receiving end: the history shown in the notifications panel and the
+/// errors currently shown as toasts.
+pub struct NotificationCenter {
+    receiver: mpsc::Receiver<Notification>,
+    history: VecDeque<Notification>,
+    toasts: VecDeque<(Notification, Instant)>,
+    unread: usize,
+}
+
+impl NotificationCenter {
+    /// Routes every later report to the returned center. Only the first
+    /// call takes effect; later centers never receive anything.
+    pub fn install() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let _ = SENDER.set(sender);
+        NotificationCenter {
+            receiver,
+            history: VecDeque::new(),
+            toasts: VecDeque::new(),
+            unread: 0,
+        }
+    }
+
+    /// Takes in everything reported since the last call and expires old toasts.
+    pub fn poll(&mut self) {
+        for notification in self.receiver.try_iter() {
+            if notification.severity == Severity::Error {
+                self.toasts.push_back((notification.clone(), Instant::now()));
+                if self.toasts.len() > TOAST_LIMIT {
+                    self.toasts.pop_front();
+                }
+            }
+            self.history.push_back(notification);
+            if self.history.len() > HISTORY_LIMIT {
+                self.history.pop_front();
+            }
+            self.unread += 1;
+        }
+        self.toasts.retain(|(_, shown)| shown.elapsed() < TOAST_DURATION);
+    }
+
+    pub fn toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.toasts.iter().map(|(notification, _)| notification)
+    }
+
+    pub fn has_toasts(&self) -> bool {
+        !self.toasts.is_empty()
+    }
+
+    pub fn dismiss_toast(&mut self, index: usize) {
+        self.toasts.remove(index);
+    }
+
+    /// Newest first.
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter().rev()
+    }
+
+    pub fn unread(&self) -> usize {
+        self.unread
+    }
+
+    pub fn mark_read(&mut self) {
+        self.unread = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.toasts.clear();
+        self.unread = 0;
+    }
+}
@@ -0,0 +1,155 @@
+//! Paths as raw OS bytes. File names on Unix are arbitrary bytes, so the
+//! index and the saved session keep those bytes and only derive a lossy
+//! string for display.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+
+pub fn to_bytes(path: &Path) -> Vec<u8> {
+    path.as_os_str().as_bytes().to_vec()
+}
+
+pub fn from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+/// True when `name` would change if shown as a Rust string.
+pub fn is_lossy(name: &OsStr) -> bool {
+    name.to_str().is_none()
+}
+
+/// Parses a path typed into a text field. While the text still reads as
+/// the display form of `original`, the original bytes are kept.
+pub fn from_edited(text: &str, original: Option<&Path>) -> PathBuf {
+    match original {
+        Some(original) if original.to_string_lossy() == text => original.to_path_buf(),
+        _ => PathBuf::from(text),
+    }
+}
+
+/// A path as written to config and session files: a plain string when it is
+/// valid UTF-8, so existing files stay readable, and a byte array otherwise.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredPath {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl From<StoredPath> for PathBuf {
+    fn from(stored: StoredPath) -> Self {
+        match stored {
+            StoredPath::Text(text) => PathBuf::from(text),
+            StoredPath::Bytes(bytes) => from_bytes(bytes),
+        }
+    }
+}
+
+fn serialize_path<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    match path.to_str() {
+        Some(text) => text.serialize(serializer),
+        None => path.as_os_str().as_bytes().serialize(serializer),
+    }
+}
+
+/// `#[serde(with = "crate::os_path::lossless")]` for `PathBuf` fields.
+pub mod lossless {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_path(path, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        StoredPath::deserialize(deserializer).map(PathBuf::from)
+    }
+}
+
+/// `#[serde(with = "crate::os_path::lossless_option")]` for `Option<PathBuf>` fields.
+pub mod lossless_option {
+    use super::*;
+
+    struct Borrowed<'a>(&'a Path);
+
+    impl Serialize for Borrowed<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_path(self.0, serializer)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(path: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error> {
+        path.as_deref().map(Borrowed).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<PathBuf>, D::Error> {
+        Option::<StoredPath>::deserialize(deserializer).map(|stored| stored.map(PathBuf::from))
+    }
+}
+
+/// `#[serde(with = "crate::os_path::lossless_set")]` for `HashSet<PathBuf>` fields.
+pub mod lossless_set {
+    use super::*;
+    use std::collections::HashSet;
+
+    struct Borrowed<'a>(&'a Path);
+
+    impl Serialize for Borrowed<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_path(self.0, serializer)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(paths: &HashSet<PathBuf>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(paths.iter().map(|path| Borrowed(path)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashSet<PathBuf>, D::Error> {
+        Vec::<StoredPath>::deserialize(deserializer).map(|stored| stored.into_iter().map(PathBuf::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Stored {
+        #[serde(with = "lossless")]
+        path: PathBuf,
+        #[serde(with = "lossless_option")]
+        parent: Option<PathBuf>,
+        #[serde(with = "lossless_set")]
+        expanded: HashSet<PathBuf>,
+    }
+
+    #[test]
+    fn non_utf8_paths_round_trip_through_json() {
+        let raw = from_bytes(b"/home/caf\xe9".to_vec());
+        let stored = Stored {
+            path: raw.clone(),
+            parent: Some(raw.clone()),
+            expanded: HashSet::from([raw, PathBuf::from("/home")]),
+        };
+        let json = serde_json::to_string(&stored).unwrap();
+        assert_eq!(serde_json::from_str::<Stored>(&json).unwrap(), stored);
+    }
+
+    #[test]
+    fn utf8_paths_are_stored_as_strings() {
+        let json = serde_json::json!({"path": "/home/me", "parent": null, "expanded": ["/tmp"]});
+        let stored: Stored = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(stored.path, Path::new("/home/me"));
+        assert_eq!(serde_json::to_value(&stored).unwrap(), json);
+    }
+
+    #[test]
+    fn edited_text_keeps_original_bytes_until_changed() {
+        let original = from_bytes(b"/caf\xe9".to_vec());
+        let shown = original.to_string_lossy().to_string();
+        assert_eq!(from_edited(&shown, Some(&original)), original);
+        assert_eq!(from_edited("/cafe", Some(&original)), Path::new("/cafe"));
+    }
+}
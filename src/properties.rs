@@ -0,0 +1,287 @@
+//! Everything the properties window shows beyond the indexed metadata:
+//! allocated size, tags and extended attributes read when it opens, plus
+//! folder totals and checksums worked out on background threads while the
+//! window stays responsive.
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::tag_db::{FileEntry, FileType, TagDatabase};
+use crate::vfs::{self, Vfs};
+
+/// Bytes shown of an extended attribute value before it is cut short.
+const XATTR_PREVIEW_LEN: usize = 200;
+
+/// Running totals of everything below a folder, filled in by the walker.
+#[derive(Default)]
+pub struct FolderSummary {
+    pub files: AtomicU64,
+    pub folders: AtomicU64,
+    pub size: AtomicU64,
+    /// Only known for folders on a local disk.
+    pub allocated: AtomicU64,
+    /// Entries that could not be read.
+    pub errors: AtomicU64,
+    pub done: AtomicBool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChecksumKind {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl ChecksumKind {
+    pub const ALL: [ChecksumKind; 4] = [
+        ChecksumKind::Md5,
+        ChecksumKind::Sha1,
+        ChecksumKind::Sha256,
+        ChecksumKind::Sha512,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChecksumKind::Md5 => "MD5",
+            ChecksumKind::Sha1 => "SHA-1",
+            ChecksumKind::Sha256 => "SHA-256",
+            ChecksumKind::Sha512 => "SHA-512",
+        }
+    }
+
+    /// The lower-case hex digest of everything `reader` yields.
+    fn digest(self, reader: &mut dyn Read, cancel: &AtomicBool) -> io::Result<String> {
+        match self {
+            ChecksumKind::Md5 => hash::<Md5>(reader, cancel),
+            ChecksumKind::Sha1 => hash::<Sha1>(reader, cancel),
+            ChecksumKind::Sha256 => hash::<Sha256>(reader, cancel),
+            ChecksumKind::Sha512 => hash::<Sha512>(reader, cancel),
+        }
+    }
+}
+
+fn hash<D: Digest>(reader: &mut dyn Read, cancel: &AtomicBool) -> io::Result<String> {
+    let mut hasher = D::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[derive(Debug, Clone)]
+pub enum ChecksumState {
+    Running,
+    Done(String),
+    Failed(String),
+}
+
+/// A file or folder as the properties window shows it. Background work
+/// stops when this is dropped.
+pub struct Properties {
+    pub file: FileEntry,
+    /// Disk space taken, which differs from the size for sparse and
+    /// compressed files. Only known on a local disk.
+    pub allocated: Option<u64>,
+    pub tags: Vec<String>,
+    /// Name and printable value of each extended attribute.
+    pub xattrs: Vec<(String, String)>,
+    pub folder: Option<Arc<FolderSummary>>,
+    pub checksums: BTreeMap<ChecksumKind, Arc<Mutex<ChecksumState>>>,
+    /// Checksums are only offered for regular files: opening a FIFO or a
+    /// device could block forever.
+    pub can_checksum: bool,
+    local: bool,
+    vfs: Arc<dyn Vfs>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Properties {
+    /// Reads `file` afresh from `vfs` and starts totalling it if it is a
+    /// folder.
+    pub fn new(file: FileEntry, vfs: Arc<dyn Vfs>, tag_db: &TagDatabase) -> Self {
+        let file = vfs.stat(&file.path).unwrap_or(file);
+        let local = matches!(vfs::Location::of_path(&file.path), vfs::Location::Local(_));
+        let allocated = local
+            .then(|| fs::symlink_metadata(&file.path).ok())
+            .flatten()
+            .map(|metadata| metadata.blocks() * 512);
+        let tags = tag_db.get_tags_for_file(&file.path).unwrap_or_else(|e| {
+            log::warn!("Could not read tags of {}: {}", file.path.display(), e);
+            Vec::new()
+        });
+        let xattrs = if local { read_xattrs(&file.path) } else { Vec::new() };
+        let can_checksum = file.file_type == FileType::File && (!local || is_regular_file(&file.path));
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let folder = (file.file_type == FileType::Directory).then(|| {
+            let summary = Arc::new(FolderSummary::default());
+            let (path, vfs, cancel) = (file.path.clone(), vfs.clone(), cancel.clone());
+            let totals = summary.clone();
+            std::thread::spawn(move || {
+                if local {
+                    walk_local(&path, &totals, &cancel);
+                } else {
+                    walk_vfs(vfs.as_ref(), &path, &totals, &cancel);
+                }
+                totals.done.store(true, Ordering::Relaxed);
+            });
+            summary
+        });
+
+        Properties {
+            file,
+            allocated,
+            tags,
+            xattrs,
+            folder,
+            checksums: BTreeMap::new(),
+            can_checksum,
+            local,
+            vfs,
+            cancel,
+        }
+    }
+
+    /// Starts computing a checksum of the file unless it already has been.
+    pub fn compute_checksum(&mut self, kind: ChecksumKind) {
+        if !self.can_checksum || self.checksums.contains_key(&kind) {
+            return;
+        }
+        let state = Arc::new(Mutex::new(ChecksumState::Running));
+        self.checksums.insert(kind, state.clone());
+
+        let (path, vfs, cancel) = (self.file.path.clone(), self.vfs.clone(), self.cancel.clone());
+        let local = self.local;
+        std::thread::spawn(move || {
+            // Checked again right before opening, in case the file was
+            // replaced while the window was open.
+            let result = if local && !is_regular_file(&path) {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "not a regular file"))
+            } else {
+                vfs.open(&path).and_then(|mut reader| kind.digest(&mut reader, &cancel))
+            };
+            *state.lock().unwrap() = match result {
+                Ok(digest) => ChecksumState::Done(digest),
+                Err(e) => ChecksumState::Failed(e.to_string()),
+            };
+        });
+    }
+
+    /// True while folder totals or checksums are still being worked out.
+    pub fn is_busy(&self) -> bool {
+        self.folder
+            .as_ref()
+            .is_some_and(|summary| !summary.done.load(Ordering::Relaxed))
+            || self
+                .checksums
+                .values()
+                .any(|state| matches!(*state.lock().unwrap(), ChecksumState::Running))
+    }
+}
+
+impl Drop for Properties {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// True for a regular file, as opposed to a folder, FIFO, socket or device.
+fn is_regular_file(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file())
+}
+
+fn read_xattrs(path: &Path) -> Vec<(String, String)> {
+    let Ok(names) = xattr::list(path) else {
+        return Vec::new();
+    };
+    let mut xattrs: Vec<(String, String)> = names
+        .map(|name| {
+            let value = match xattr::get(path, &name) {
+                Ok(Some(value)) => match std::str::from_utf8(&value).map(|text| text.trim_end_matches('\0')) {
+                    Ok(text) if !text.chars().any(|c| c.is_control() && c != '\n' && c != '\t') => {
+                        match text.char_indices().nth(XATTR_PREVIEW_LEN) {
+                            Some((end, _)) => format!("{}…", &text[..end]),
+                            None => text.to_string(),
+                        }
+                    }
+                    _ => format!("{} bytes", value.len()),
+                },
+                Ok(None) => String::new(),
+                Err(e) => format!("unreadable: {}", e),
+            };
+            (name.to_string_lossy().to_string(), value)
+        })
+        .collect();
+    xattrs.sort();
+    xattrs
+}
+
+/// Totals a local folder without following symbolic links, so links to
+/// folders count as one item and loops cannot happen.
+fn walk_local(dir: &Path, summary: &FolderSummary, cancel: &AtomicBool) {
+    let mut pending: Vec<PathBuf> = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            summary.errors.fetch_add(1, Ordering::Relaxed);
+            continue;
+        };
+        for entry in entries {
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            let Ok((path, metadata)) = entry.and_then(|entry| Ok((entry.path(), entry.metadata()?))) else {
+                summary.errors.fetch_add(1, Ordering::Relaxed);
+                continue;
+            };
+            if metadata.is_dir() {
+                summary.folders.fetch_add(1, Ordering::Relaxed);
+                pending.push(path);
+            } else {
+                summary.files.fetch_add(1, Ordering::Relaxed);
+                summary.size.fetch_add(metadata.len(), Ordering::Relaxed);
+            }
+            summary.allocated.fetch_add(metadata.blocks() * 512, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Totals a folder of any other backend through its listings.
+fn walk_vfs(vfs: &dyn Vfs, dir: &Path, summary: &FolderSummary, cancel: &AtomicBool) {
+    let mut pending: Vec<PathBuf> = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(entries) = vfs.list(&dir) else {
+            summary.errors.fetch_add(1, Ordering::Relaxed);
+            continue;
+        };
+        for entry in entries {
+            if entry.file_type == FileType::Directory {
+                summary.folders.fetch_add(1, Ordering::Relaxed);
+                pending.push(entry.path);
+            } else {
+                summary.files.fetch_add(1, Ordering::Relaxed);
+                summary.size.fetch_add(entry.size, Ordering::Relaxed);
+            }
+        }
+    }
+}
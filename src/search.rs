@@ -1,8 +1,88 @@
-use std::path::PathBuf;
+use std::path::Path;
 use std::sync::Arc;
-use rusqlite::{Result, params};
+use rusqlite::{Result, params_from_iter};
+use rusqlite::types::Value;
+
+use crate::file_kind::FileKind;
+use crate::tag_db::{TagDatabase, FileEntry, file_entry_from_row, normalize_path, FILE_COLUMNS};
+
+/// A search string split into free text and `key:value` filters, e.g.
+/// `report ext:pdf owner:alice kind:image`. Unknown keys and kinds are
+/// treated as text.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub text: String,
+    pub extension: Option<String>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub kind: Option<FileKind>,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = SearchQuery::default();
+        let mut words = Vec::new();
+        for word in query.split_whitespace() {
+            match word.split_once(':') {
+                Some(("ext", value)) if !value.is_empty() => {
+                    parsed.extension = Some(value.trim_start_matches('.').to_lowercase());
+                }
+                Some(("owner", value)) if !value.is_empty() => parsed.owner = Some(value.to_string()),
+                Some(("group", value)) if !value.is_empty() => parsed.group = Some(value.to_string()),
+                Some(("kind", value)) if FileKind::from_name(value).is_some() => {
+                    parsed.kind = FileKind::from_name(value);
+                }
+                _ => words.push(word),
+            }
+        }
+        parsed.text = words.join(" ");
+        parsed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == SearchQuery::default()
+    }
+
+    /// SQL conditions on `files f`; their values are appended to `values`
+    /// in placeholder order.
+    fn conditions(&self, values: &mut Vec<Value>) -> Vec<&'static str> {
+        let mut conditions = Vec::new();
+        if !self.text.is_empty() {
+            conditions.push("(LOWER(f.name) LIKE LOWER(?) OR LOWER(f.display_path) LIKE LOWER(?))");
+            let pattern = format!("%{}%", self.text);
+            values.push(pattern.clone().into());
+            values.push(pattern.into());
+        }
+        if let Some(extension) = &self.extension {
+            conditions.push("f.extension = ?");
+            values.push(extension.clone().into());
+        }
+        if let Some(owner) = &self.owner {
+            conditions.push("f.owner = ?");
+            values.push(owner.clone().into());
+        }
+        if let Some(group) = &self.group {
+            conditions.push("f.group_name = ?");
+            values.push(group.clone().into());
+        }
+        if let Some(kind) = self.kind {
+            conditions.push("f.kind = ?");
+            values.push(kind.name().to_string().into());
+        }
+        conditions
+    }
 
-use crate::tag_db::{TagDatabase, FileEntry, normalize_path};
+    pub fn matches(&self, file: &FileEntry) -> bool {
+        let text = self.text.to_lowercase();
+        (text.is_empty()
+            || file.name.to_lowercase().contains(&text)
+            || file.path.to_string_lossy().to_lowercase().contains(&text))
+            && self.extension.as_ref().is_none_or(|ext| file.metadata.extension.as_ref() == Some(ext))
+            && self.owner.as_ref().is_none_or(|owner| file.metadata.owner.as_ref() == Some(owner))
+            && self.group.as_ref().is_none_or(|group| file.metadata.group.as_ref() == Some(group))
+            && self.kind.is_none_or(|kind| file.metadata.kind == Some(kind))
+    }
+}
 
 pub struct SearchEngine {
     pub(crate) tag_db: Arc<TagDatabase>,
@@ -14,70 +94,54 @@ impl SearchEngine {
     }
 
     pub fn search(&self, query: &str) -> Result<Vec<FileEntry>> {
+        let query = SearchQuery::parse(query);
         if query.is_empty() {
             return Ok(vec![]);
         }
 
         let conn = self.tag_db.conn.lock().unwrap();
-        let search_pattern = format!("%{}%", query);
-
-        let mut stmt = conn.prepare(
-            "SELECT DISTINCT path, name, file_type, size, modified, parent
-             FROM files
-             WHERE LOWER(name) LIKE LOWER(?1) OR LOWER(path) LIKE LOWER(?1)
-             ORDER BY name
-             LIMIT 1000"
-        )?;
-
-        let files = stmt.query_map(params![search_pattern], |row| {
-            Ok(FileEntry {
-                path: PathBuf::from(row.get::<_, String>(0)?),
-                name: row.get(1)?,
-                file_type: match row.get::<_, String>(2)?.as_str() {
-                    "file" => crate::tag_db::FileType::File,
-                    "directory" => crate::tag_db::FileType::Directory,
-                    _ => crate::tag_db::FileType::File,
-                },
-                size: row.get(3)?,
-                modified: row.get(4)?,
-                parent: row.get::<_, Option<String>>(5)?.map(PathBuf::from),
-            })
-        })?
+        self.tag_db.refresh_stale_frecency(&conn)?;
+        let mut values = Vec::new();
+        let conditions = query.conditions(&mut values);
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM files f
+             LEFT JOIN frecency fr ON fr.path = f.path
+             WHERE {}
+             ORDER BY COALESCE(fr.score, 0) DESC, f.name
+             LIMIT 1000",
+            FILE_COLUMNS, conditions.join(" AND ")
+        ))?;
+
+        let files = stmt.query_map(params_from_iter(values), file_entry_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
 
         Ok(files)
     }
 
-    pub fn search_in_directory(&self, dir_path: &PathBuf, query: &str) -> Result<Vec<FileEntry>> {
+    pub fn search_in_directory(&self, dir_path: &Path, query: &str) -> Result<Vec<FileEntry>> {
+        let query = SearchQuery::parse(query);
         if query.is_empty() {
             return self.tag_db.get_files_in_directory(dir_path);
         }
 
         let conn = self.tag_db.conn.lock().unwrap();
-        let search_pattern = format!("%{}%", query);
-
-        let mut stmt = conn.prepare(
-            "SELECT path, name, file_type, size, modified, parent
-             FROM files
-             WHERE parent = ?1 AND (LOWER(name) LIKE LOWER(?2) OR LOWER(path) LIKE LOWER(?2))
-             ORDER BY file_type DESC, name
-             LIMIT 1000"
-        )?;
-
-        let files = stmt.query_map(params![normalize_path(dir_path), search_pattern], |row| {
-            Ok(FileEntry {
-                path: PathBuf::from(row.get::<_, String>(0)?),
-                name: row.get(1)?,
-                file_type: match row.get::<_, String>(2)?.as_str() {
-                    "file" => crate::tag_db::FileType::File,
-                    "directory" => crate::tag_db::FileType::Directory,
-                    _ => crate::tag_db::FileType::File,
-                },
-                size: row.get(3)?,
-                modified: row.get(4)?,
-                parent: row.get::<_, Option<String>>(5)?.map(PathBuf::from),
-            })
-        })?
+        self.tag_db.refresh_stale_frecency(&conn)?;
+        let mut values = vec![Value::Blob(normalize_path(dir_path))];
+        let conditions = query.conditions(&mut values);
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM files f
+             LEFT JOIN frecency fr ON fr.path = f.path
+             WHERE f.parent = ? AND {}
+             ORDER BY COALESCE(fr.score, 0) DESC, f.file_type DESC, f.name
+             LIMIT 1000",
+            FILE_COLUMNS, conditions.join(" AND ")
+        ))?;
+
+        let files = stmt.query_map(params_from_iter(values), file_entry_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
 
         Ok(files)
@@ -86,14 +150,62 @@ impl SearchEngine {
     pub fn search_by_tag(&self, tag_name: &str, query: &str) -> Result<Vec<FileEntry>> {
         let mut files = self.tag_db.get_files_by_tag(tag_name)?;
 
+        let query = SearchQuery::parse(query);
+        if !query.is_empty() {
+            files.retain(|f| query.matches(f));
+
+            let scores = self.tag_db.get_frecency_scores()?;
+            files.sort_by_key(|f| std::cmp::Reverse(scores.get(&f.path).copied().unwrap_or(0)));
+        }
+
+        Ok(files)
+    }
+
+    pub fn search_by_kind(&self, kind: FileKind, query: &str) -> Result<Vec<FileEntry>> {
+        let mut files = self.tag_db.get_files_by_kind(kind)?;
+
+        let query = SearchQuery::parse(query);
+        if !query.is_empty() {
+            files.retain(|f| query.matches(f));
+
+            let scores = self.tag_db.get_frecency_scores()?;
+            files.sort_by_key(|f| std::cmp::Reverse(scores.get(&f.path).copied().unwrap_or(0)));
+        }
+
+        Ok(files)
+    }
+
+    pub fn search_recent(&self, query: &str) -> Result<Vec<FileEntry>> {
+        let mut files = self.tag_db.get_recent_files(500)?;
+
+        let query = SearchQuery::parse(query);
         if !query.is_empty() {
-            let query_lower = query.to_lowercase();
-            files.retain(|f| {
-                f.name.to_lowercase().contains(&query_lower) ||
-                f.path.to_string_lossy().to_lowercase().contains(&query_lower)
-            });
+            files.retain(|f| query.matches(f));
         }
 
         Ok(files)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_splits_filters_from_text() {
+        let query = SearchQuery::parse("annual report ext:.PDF owner:alice kind:document group:");
+        assert_eq!(query.text, "annual report group:");
+        assert_eq!(query.extension.as_deref(), Some("pdf"));
+        assert_eq!(query.owner.as_deref(), Some("alice"));
+        assert_eq!(query.group, None);
+        assert_eq!(query.kind, Some(FileKind::Document));
+    }
+
+    #[test]
+    fn unknown_kinds_stay_text() {
+        let query = SearchQuery::parse("kind:banana");
+        assert_eq!(query.text, "kind:banana");
+        assert_eq!(query.kind, None);
+        assert!(SearchQuery::parse("   ").is_empty());
+    }
+}
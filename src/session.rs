@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+pub const SESSION_STORAGE_KEY: &str = "session";
+
+pub const DEFAULT_SIDEBAR_WIDTH: f32 = 150.0;
+pub const DEFAULT_TAG_LIST_WIDTH: f32 = 200.0;
+
+/// Window-wide state restored on the next launch. Per-tab state such as the
+/// view and folder lives in the saved `TabSet`; the window geometry is kept
+/// by eframe itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub show_directory_tree: bool,
+    pub show_hidden_files: bool,
+    pub show_ignored_files: bool,
+    pub directory_search_mode: bool,
+    #[serde(with = "crate::os_path::lossless_set")]
+    pub expanded_directories: HashSet<PathBuf>,
+    pub sidebar_width: f32,
+    pub tag_list_width: f32,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            show_directory_tree: true,
+            show_hidden_files: false,
+            show_ignored_files: false,
+            directory_search_mode: false,
+            expanded_directories: HashSet::new(),
+            sidebar_width: DEFAULT_SIDEBAR_WIDTH,
+            tag_list_width: DEFAULT_TAG_LIST_WIDTH,
+        }
+    }
+}
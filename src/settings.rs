@@ -0,0 +1,231 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::notifications;
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    std::env::var("HOME").ok().map(PathBuf::from)
+}
+
+/// `~/.fms`, which holds every fms config file.
+pub(crate) fn config_dir() -> PathBuf {
+    home_dir().unwrap_or_else(|| PathBuf::from("/")).join(".fms")
+}
+
+/// Rewrites paths under `$HOME` as `~/...`, so config files stay portable
+/// between machines with different home directories.
+pub(crate) fn contract_home(path: &Path) -> PathBuf {
+    match home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) => PathBuf::from("~").join(relative),
+        None => path.to_path_buf(),
+    }
+}
+
+pub(crate) fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), home_dir()) {
+        (Ok(relative), Some(home)) => home.join(relative),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexRoot {
+    #[serde(with = "crate::os_path::lossless")]
+    pub path: PathBuf,
+    /// `None` walks the whole tree.
+    pub max_depth: Option<usize>,
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    #[serde(default)]
+    pub same_filesystem: bool,
+    /// Honor `.gitignore`, `.ignore` and `.fmsignore` files under this root.
+    #[serde(default = "default_true")]
+    pub respect_ignore_files: bool,
+    /// Glob patterns matched against both the entry name and its full path.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// List the contents of zip and tar archives so search finds them.
+    #[serde(default)]
+    pub index_archives: bool,
+}
+
+impl IndexRoot {
+    pub fn new(path: PathBuf, max_depth: Option<usize>) -> Self {
+        IndexRoot {
+            path,
+            max_depth,
+            follow_symlinks: false,
+            same_filesystem: false,
+            respect_ignore_files: true,
+            exclude: Vec::new(),
+            index_archives: false,
+        }
+    }
+
+    pub fn exclude_matcher(&self) -> GlobSet {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.exclude {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => notifications::error!(format!("Invalid exclude pattern {:?}: {}", pattern, e), Some(&self.path)),
+            }
+        }
+        builder.build().unwrap_or_else(|e| {
+            notifications::error!(format!("Could not build exclude patterns: {}", e), Some(&self.path));
+            GlobSet::empty()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexSettings {
+    pub roots: Vec<IndexRoot>,
+}
+
+impl Default for IndexSettings {
+    fn default() -> Self {
+        let mut home = IndexRoot::new(PathBuf::from("~"), Some(3));
+        home.exclude = vec![
+            "node_modules".to_string(),
+            "target".to_string(),
+            ".cache".to_string(),
+        ];
+
+        IndexSettings {
+            roots: vec![IndexRoot::new(PathBuf::from("/"), Some(1)), home],
+        }
+    }
+}
+
+/// What counts as hidden besides dot files; see `crate::hidden`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HiddenSettings {
+    /// Glob patterns matched against both the entry name and its full path.
+    pub patterns: Vec<String>,
+    /// Hide editor backup files ending in `~`.
+    pub hide_backups: bool,
+    /// Hide names listed in a directory's `.hidden` file (GNOME/KDE).
+    pub honor_hidden_files: bool,
+}
+
+impl Default for HiddenSettings {
+    fn default() -> Self {
+        HiddenSettings {
+            patterns: Vec::new(),
+            hide_backups: true,
+            honor_hidden_files: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppearanceSettings {
+    /// A theme name, or `"system"` to pick light or dark to match the OS.
+    pub theme: String,
+}
+
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        AppearanceSettings {
+            theme: crate::ui::theme::FOLLOW_SYSTEM.to_string(),
+        }
+    }
+}
+
+/// See `crate::logging`; the `FMS_LOG` environment variable overrides the
+/// levels set here.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingSettings {
+    /// `error`, `warn`, `info`, `debug`, `trace` or `off`.
+    pub level: String,
+    /// Levels for individual modules, e.g. `"fms::indexer": "debug"`.
+    pub targets: BTreeMap<String, String>,
+    /// `fms.log` is rotated once it would grow past this size.
+    pub max_file_size_kb: u64,
+    /// How many rotated files (`fms.log.1`, ...) are kept.
+    pub max_files: usize,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        LoggingSettings {
+            level: "info".to_string(),
+            targets: BTreeMap::new(),
+            max_file_size_kb: 5 * 1024,
+            max_files: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub index: IndexSettings,
+    pub hidden: HiddenSettings,
+    pub appearance: AppearanceSettings,
+    pub logging: LoggingSettings,
+    #[serde(skip)]
+    config_path: PathBuf,
+}
+
+impl Settings {
+    pub fn load(config_dir: &Path) -> Self {
+        let config_path = config_dir.join("settings.json");
+        let mut settings = Self::load_config(&config_path);
+        settings.config_path = config_path;
+        for root in &mut settings.index.roots {
+            root.path = expand_home(&root.path);
+        }
+        settings
+    }
+
+    fn load_config(config_path: &Path) -> Settings {
+        if !config_path.exists() {
+            return Settings::default();
+        }
+
+        match std::fs::read_to_string(config_path) {
+            Ok(content) => match serde_json::from_str::<Settings>(&content) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    notifications::error!(format!("Could not parse settings: {}", e), Some(config_path));
+                    Settings::default()
+                }
+            },
+            Err(e) => {
+                notifications::error!(format!("Could not read settings: {}", e), Some(config_path));
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let mut portable = self.clone();
+        for root in &mut portable.index.roots {
+            root.path = contract_home(&root.path);
+        }
+
+        let result = serde_json::to_string_pretty(&portable)
+            .map_err(std::io::Error::from)
+            .and_then(|json| {
+                if let Some(parent) = self.config_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&self.config_path, json)
+            });
+        if let Err(e) = result {
+            notifications::error!(format!("Could not save settings: {}", e), Some(&self.config_path));
+        }
+    }
+}
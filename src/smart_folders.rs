@@ -0,0 +1,138 @@
+use rusqlite::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::notifications;
+use crate::search::SearchEngine;
+use crate::tag_db::FileEntry;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SmartScope {
+    Everywhere,
+    Directory {
+        #[serde(with = "crate::os_path::lossless")]
+        path: PathBuf,
+    },
+    Tag { tag: String },
+}
+
+/// A named, saved search. Its contents are never stored; they are
+/// re-evaluated against the index every time the folder is shown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartFolder {
+    pub name: String,
+    pub query: String,
+    pub scope: SmartScope,
+}
+
+impl SmartFolder {
+    pub fn evaluate(&self, search_engine: &SearchEngine) -> Result<Vec<FileEntry>> {
+        match &self.scope {
+            SmartScope::Everywhere => search_engine.search(&self.query),
+            SmartScope::Directory { path } => search_engine.search_in_directory(path, &self.query),
+            SmartScope::Tag { tag } => search_engine.search_by_tag(tag, &self.query),
+        }
+    }
+
+    pub fn describe_scope(&self) -> String {
+        match &self.scope {
+            SmartScope::Everywhere => "everywhere".to_string(),
+            SmartScope::Directory { path } => format!("in {}", path.display()),
+            SmartScope::Tag { tag } => format!("tagged {}", tag),
+        }
+    }
+}
+
+pub struct SmartFolders {
+    folders: Vec<SmartFolder>,
+    config_path: PathBuf,
+    /// Where single folders are exported to.
+    export_dir: PathBuf,
+}
+
+impl SmartFolders {
+    pub fn new(config_dir: &Path) -> Self {
+        let config_path = config_dir.join("smart_folders.json");
+        let folders = Self::load_config(&config_path);
+
+        SmartFolders {
+            folders,
+            config_path,
+            export_dir: config_dir.join("smart_folders"),
+        }
+    }
+
+    fn load_config(config_path: &Path) -> Vec<SmartFolder> {
+        if !config_path.exists() {
+            return Vec::new();
+        }
+
+        match std::fs::read_to_string(config_path) {
+            Ok(content) => match serde_json::from_str::<Vec<SmartFolder>>(&content) {
+                Ok(folders) => folders,
+                Err(e) => {
+                    notifications::error!(format!("Could not parse smart folders: {}", e), Some(config_path));
+                    Vec::new()
+                }
+            },
+            Err(e) => {
+                notifications::error!(format!("Could not read smart folders: {}", e), Some(config_path));
+                Vec::new()
+            }
+        }
+    }
+
+    fn save(&self) {
+        if let Err(e) = write_json(&self.config_path, &self.folders) {
+            notifications::error!(format!("Could not save smart folders: {}", e), Some(&self.config_path));
+        }
+    }
+
+    pub fn folders(&self) -> &[SmartFolder] {
+        &self.folders
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SmartFolder> {
+        self.folders.iter().find(|f| f.name == name)
+    }
+
+    /// Inserts `folder`, replacing the one called `previous_name` (when
+    /// editing) or any existing folder with the same name.
+    pub fn upsert(&mut self, previous_name: Option<&str>, folder: SmartFolder) {
+        let replace_name = previous_name.unwrap_or(&folder.name).to_string();
+        self.folders.retain(|f| f.name != replace_name && f.name != folder.name);
+        self.folders.push(folder);
+        self.folders.sort_by_key(|f| f.name.to_lowercase());
+        self.save();
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.folders.retain(|f| f.name != name);
+        self.save();
+    }
+
+    /// Writes a single smart folder as `<name>.json` into the export
+    /// folder next to the config, `~/.fms/smart_folders/`.
+    pub fn export(&self, name: &str) -> std::io::Result<PathBuf> {
+        let folder = self.get(name).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, format!("no smart folder named {}", name))
+        })?;
+        let file_name: String = folder
+            .name
+            .chars()
+            .map(|c| if c == '/' || c == '\0' { '_' } else { c })
+            .collect();
+        let path = self.export_dir.join(format!("{}.json", file_name));
+        write_json(&path, folder)?;
+        Ok(path)
+    }
+}
+
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, json)
+}
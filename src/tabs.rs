@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::file_kind::FileKind;
+use crate::ui::file_list::{ListScroll, SortOrder};
+
+pub const TABS_STORAGE_KEY: &str = "tabs";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ViewTab {
+    Folders,
+    Tags,
+    Recent,
+    Smart,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tab {
+    pub view: ViewTab,
+    #[serde(with = "crate::os_path::lossless")]
+    pub folder_current_path: PathBuf,
+    pub search_query: String,
+    pub selected_file_index: Option<usize>,
+    pub tag_selected: Option<String>,
+    #[serde(default)]
+    pub kind_selected: Option<FileKind>,
+    #[serde(default)]
+    pub smart_folder: Option<String>,
+    pub scroll: ListScroll,
+    #[serde(default)]
+    pub sort: SortOrder,
+    #[serde(skip)]
+    pub last_search_query: String,
+}
+
+impl Tab {
+    pub fn new(folder_current_path: PathBuf) -> Self {
+        Tab {
+            view: ViewTab::Folders,
+            folder_current_path,
+            search_query: String::new(),
+            selected_file_index: None,
+            tag_selected: None,
+            kind_selected: None,
+            smart_folder: None,
+            scroll: ListScroll::default(),
+            sort: SortOrder::default(),
+            last_search_query: String::new(),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        if !self.search_query.is_empty() {
+            return format!("\u{1F50D} {}", self.search_query);
+        }
+
+        match self.view {
+            ViewTab::Folders => self
+                .folder_current_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.folder_current_path.to_string_lossy().to_string()),
+            ViewTab::Tags => match (&self.tag_selected, self.kind_selected) {
+                (Some(tag), _) => format!("# {}", tag),
+                (None, Some(kind)) => format!("{} {}", kind.icon(), kind.label()),
+                (None, None) => "Tags".to_string(),
+            },
+            ViewTab::Recent => "Recent".to_string(),
+            ViewTab::Smart => match &self.smart_folder {
+                Some(name) => format!("🗂 {}", name),
+                None => "Smart folders".to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabSet {
+    tabs: Vec<Tab>,
+    active: usize,
+}
+
+impl TabSet {
+    pub fn new(initial: Tab) -> Self {
+        TabSet {
+            tabs: vec![initial],
+            active: 0,
+        }
+    }
+
+    /// Fixes up a set loaded from storage: guarantees at least one tab, a
+    /// valid active index, and schedules every tab's scroll offset to be
+    /// re-applied on its next render.
+    pub fn restored(mut self, fallback: Tab) -> Self {
+        if self.tabs.is_empty() {
+            self.tabs.push(fallback);
+        }
+        self.active = self.active.min(self.tabs.len() - 1);
+        for tab in &mut self.tabs {
+            tab.last_search_query = tab.search_query.clone();
+            tab.scroll.pending_restore = true;
+        }
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Tab> {
+        self.tabs.iter()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &Tab {
+        &self.tabs[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active]
+    }
+
+    /// Opens `tab` right after the active one and switches to it.
+    pub fn open(&mut self, tab: Tab) {
+        self.tabs.insert(self.active + 1, tab);
+        self.select(self.active + 1);
+    }
+
+    /// Closes the tab at `index`. The last remaining tab is never closed.
+    pub fn close(&mut self, index: usize) -> bool {
+        if self.tabs.len() <= 1 || index >= self.tabs.len() {
+            return false;
+        }
+
+        self.tabs.remove(index);
+        if self.active > index || self.active >= self.tabs.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        self.active_mut().scroll.pending_restore = true;
+        true
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.tabs.len() && index != self.active {
+            self.active = index;
+            self.active_mut().scroll.pending_restore = true;
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        self.select((self.active + 1) % self.tabs.len());
+    }
+
+    pub fn select_previous(&mut self) {
+        self.select((self.active + self.tabs.len() - 1) % self.tabs.len());
+    }
+}
@@ -1,30 +1,327 @@
-use rusqlite::{Connection, Result, params};
-use std::path::PathBuf;
+use rusqlite::{Connection, Result, Row, params};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 
-pub(crate) fn normalize_path(path: &PathBuf) -> String {
-    let mut normalized = path.to_string_lossy().to_string();
-    if normalized.ends_with('/') && normalized.len() > 1 {
+use crate::file_kind::FileKind;
+use crate::notifications;
+use crate::os_path;
+
+/// The index key of `path`: its exact OS bytes without a trailing slash.
+/// Paths are stored as BLOBs so names that are not valid UTF-8 round-trip
+/// unchanged; `display_path` holds the lossy text used for searching.
+pub(crate) fn normalize_path(path: &Path) -> Vec<u8> {
+    let mut normalized = os_path::to_bytes(path);
+    if normalized.ends_with(b"/") && normalized.len() > 1 {
         normalized.pop();
     }
     normalized
 }
 
+/// Scores each path by its visits, each weighted by how long ago it was, so
+/// items used both often and recently score highest. The results are kept
+/// in the `frecency` table for queries to join against. Append `GROUP BY
+/// path`, optionally preceded by a `WHERE`.
+const FRECENCY_SQL: &str = "SELECT path,
+        SUM(CASE
+            WHEN now - visited_at < 4 * 86400 THEN 100
+            WHEN now - visited_at < 14 * 86400 THEN 70
+            WHEN now - visited_at < 31 * 86400 THEN 50
+            WHEN now - visited_at < 90 * 86400 THEN 30
+            ELSE 10
+        END) AS score,
+        MAX(visited_at) AS last_visited
+     FROM usage.visits, (SELECT CAST(strftime('%s', 'now') AS INTEGER) AS now)";
+
+const VISIT_RETENTION_SECS: i64 = 180 * 86400;
+
+/// How often every score is recomputed. A visit only updates its own path,
+/// so without this the other scores would keep the age weights they had
+/// when last computed.
+const FRECENCY_REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// The columns compared to decide whether a re-scanned entry changed.
+/// Access time is left out on purpose: reading a file would otherwise
+/// count as a change on every scan.
+#[derive(PartialEq)]
+struct RowState {
+    file_type: String,
+    size: u64,
+    modified: i64,
+    ignored: bool,
+    link_target: Option<Vec<u8>>,
+    link_target_type: Option<String>,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    nlink: u64,
+    flags: u32,
+}
+
+impl RowState {
+    const COLUMNS: &'static str =
+        "file_type, size, modified, ignored, link_target, link_target_type, mode, uid, gid, nlink, flags";
+
+    /// Reads `RowState::COLUMNS`, starting at column `first`.
+    fn from_row(row: &Row, first: usize) -> Result<Self> {
+        Ok(RowState {
+            file_type: row.get(first)?,
+            size: row.get(first + 1)?,
+            modified: row.get(first + 2)?,
+            ignored: row.get(first + 3)?,
+            link_target: row.get(first + 4)?,
+            link_target_type: row.get(first + 5)?,
+            mode: row.get(first + 6)?,
+            uid: row.get(first + 7)?,
+            gid: row.get(first + 8)?,
+            nlink: row.get(first + 9)?,
+            flags: row.get(first + 10)?,
+        })
+    }
+
+    fn of(entry: &FileEntry) -> Self {
+        RowState {
+            file_type: entry.file_type.name().to_string(),
+            size: entry.size,
+            modified: entry.modified,
+            ignored: entry.ignored,
+            link_target: entry.link.as_ref().map(|link| os_path::to_bytes(&link.target)),
+            link_target_type: entry
+                .link
+                .as_ref()
+                .and_then(|link| link.target_type)
+                .map(|t| t.name().to_string()),
+            mode: entry.metadata.mode,
+            uid: entry.metadata.uid,
+            gid: entry.metadata.gid,
+            nlink: entry.metadata.nlink,
+            flags: entry.metadata.flags,
+        }
+    }
+}
+
+/// Deletes `path` and every indexed entry below it, including their
+/// full-text and tag rows. Returns the number of entries removed.
+fn remove_subtree(conn: &Connection, path: &[u8]) -> Result<usize> {
+    // ?2 is the path with a trailing slash; BLOBs compare byte by byte.
+    const SUBTREE: &str = "(path = ?1 OR substr(path, 1, length(?2)) = ?2)";
+    let prefix = [path, b"/"].concat();
+
+    // External-content FTS rows must be deleted with their old values.
+    conn.prepare_cached(&format!(
+        "INSERT INTO files_fts (files_fts, rowid, name, display_path)
+         SELECT 'delete', rowid, name, display_path FROM files WHERE {}",
+        SUBTREE
+    ))?
+    .execute(params![path, prefix])?;
+    conn.prepare_cached(&format!(
+        "DELETE FROM file_tags WHERE file_path IN (SELECT path FROM files WHERE {})",
+        SUBTREE
+    ))?
+    .execute(params![path, prefix])?;
+    conn.prepare_cached(&format!("DELETE FROM files WHERE {}", SUBTREE))?
+        .execute(params![path, prefix])
+}
+
+/// Columns read by `file_entry_from_row`, for queries aliasing `files` as `f`.
+pub(crate) const FILE_COLUMNS: &str =
+    "f.path, f.name, f.file_type, f.size, f.modified, f.parent, f.ignored, f.link_target, f.link_target_type,
+     f.mode, f.uid, f.gid, f.owner, f.group_name, f.created, f.accessed, f.device, f.inode, f.nlink, f.extension,
+     f.mime, f.kind, f.flags";
+
+pub(crate) fn file_entry_from_row(row: &Row) -> Result<FileEntry> {
+    let link_target: Option<Vec<u8>> = row.get(7)?;
+    let link_target_type: Option<String> = row.get(8)?;
+    Ok(FileEntry {
+        path: os_path::from_bytes(row.get(0)?),
+        name: row.get(1)?,
+        file_type: FileType::from_name(&row.get::<_, String>(2)?),
+        size: row.get(3)?,
+        modified: row.get(4)?,
+        parent: row.get::<_, Option<Vec<u8>>>(5)?.map(os_path::from_bytes),
+        ignored: row.get(6)?,
+        link: link_target.map(|target| LinkInfo {
+            target: os_path::from_bytes(target),
+            target_type: link_target_type.as_deref().map(FileType::from_name),
+        }),
+        metadata: FileMetadata {
+            mode: row.get(9)?,
+            uid: row.get(10)?,
+            gid: row.get(11)?,
+            owner: row.get(12)?,
+            group: row.get(13)?,
+            created: row.get(14)?,
+            accessed: row.get(15)?,
+            // Stored bit for bit as signed, since SQLite has no unsigned integers.
+            device: row.get::<_, i64>(16)? as u64,
+            inode: row.get::<_, i64>(17)? as u64,
+            nlink: row.get(18)?,
+            extension: row.get(19)?,
+            mime: row.get(20)?,
+            kind: row.get::<_, Option<String>>(21)?.as_deref().and_then(FileKind::from_name),
+            flags: row.get(22)?,
+        },
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
+    #[serde(with = "crate::os_path::lossless")]
     pub path: PathBuf,
+    /// For display; lossy when the name is not valid UTF-8, see `has_lossy_name`.
     pub name: String,
     pub file_type: FileType,
     pub size: u64,
     pub modified: i64,
+    #[serde(with = "crate::os_path::lossless_option")]
     pub parent: Option<PathBuf>,
+    /// Matched by a `.gitignore`, `.ignore` or `.fmsignore` rule.
+    pub ignored: bool,
+    /// Set for `FileType::Symlink` entries.
+    pub link: Option<LinkInfo>,
+    pub metadata: FileMetadata,
+}
+
+/// Ownership, permissions and identity as reported by the filesystem.
+/// For symlinks these describe the target unless the link is broken.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    /// Permission and file-type bits (`st_mode`).
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    /// Birth time; not every filesystem records one.
+    pub created: Option<i64>,
+    pub accessed: i64,
+    pub device: u64,
+    pub inode: u64,
+    pub nlink: u64,
+    /// BSD file flags of the entry itself, not of a link's target; always 0
+    /// where the platform has none.
+    pub flags: u32,
+    /// Lower-cased and without the dot; `None` for folders.
+    pub extension: Option<String>,
+    /// Detected from the content signature or, failing that, the extension.
+    pub mime: Option<String>,
+    /// `None` for folders.
+    pub kind: Option<FileKind>,
+}
+
+impl FileMetadata {
+    /// `rwxr-xr-x` style rendering of the permission bits.
+    pub fn permissions_string(&self) -> String {
+        let mut out = String::with_capacity(9);
+        for shift in [6, 3, 0] {
+            let bits = (self.mode >> shift) & 0o7;
+            out.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            out.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            out.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+        }
+        out
+    }
+}
+
+impl FileEntry {
+    /// True for directories and for symlinks that resolve to one.
+    pub fn is_dir(&self) -> bool {
+        match self.file_type {
+            FileType::Directory => true,
+            FileType::Symlink => self
+                .link
+                .as_ref()
+                .is_some_and(|link| link.target_type == Some(FileType::Directory)),
+            FileType::File => false,
+        }
+    }
+
+    pub fn is_broken_link(&self) -> bool {
+        self.link.as_ref().is_some_and(|link| link.target_type.is_none())
+    }
+
+    /// True when the name on disk is not valid UTF-8, so `name` shows
+    /// replacement characters; `path` still holds the exact bytes.
+    pub fn has_lossy_name(&self) -> bool {
+        self.path.file_name().is_some_and(os_path::is_lossy)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LinkInfo {
+    /// The target as stored in the link, possibly relative to its folder.
+    #[serde(with = "crate::os_path::lossless")]
+    pub target: PathBuf,
+    /// What the link resolves to; `None` when the link is broken.
+    pub target_type: Option<FileType>,
+}
+
+/// An entry as produced by the indexer, together with its Finder tags.
+#[derive(Debug, Clone)]
+pub struct IndexedFile {
+    pub entry: FileEntry,
+    pub tags: Vec<String>,
+}
+
+/// The entries read from one directory listing.
+#[derive(Debug, Clone)]
+pub struct DirectoryScan {
+    pub dir: PathBuf,
+    pub entries: Vec<IndexedFile>,
+    /// Every entry of `dir` was read, so indexed children missing from
+    /// `entries` no longer exist and can be pruned.
+    pub complete: bool,
+}
+
+/// The MIME type and kind an indexed file was classified as, with the size
+/// and modification time it had then.
+#[derive(Debug, Clone)]
+pub struct Classified {
+    pub size: u64,
+    pub modified: i64,
+    pub mime: Option<String>,
+    pub kind: Option<FileKind>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyncStats {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub tags_changed: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum FileType {
     File,
     Directory,
+    Symlink,
+}
+
+impl FileType {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            FileType::File => "file",
+            FileType::Directory => "directory",
+            FileType::Symlink => "symlink",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Self {
+        match name {
+            "directory" => FileType::Directory,
+            "symlink" => FileType::Symlink,
+            _ => FileType::File,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum VisitKind {
+    Opened,
+    Entered,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,20 +333,45 @@ pub struct Tag {
 
 pub struct TagDatabase {
     pub(crate) conn: Arc<Mutex<Connection>>,
+    frecency_refreshed: Mutex<Instant>,
 }
 
 impl TagDatabase {
     pub fn new() -> Result<Self> {
+        Self::with_usage_store(Path::new(":memory:"))
+    }
+
+    /// The file index itself is in memory; visit history lives in the SQLite
+    /// file at `usage_path` (attached as `usage`) so it survives restarts.
+    pub fn with_usage_store(usage_path: &Path) -> Result<Self> {
         let conn = Connection::open_in_memory()?;
         
         conn.execute(
             "CREATE TABLE IF NOT EXISTS files (
-                path TEXT PRIMARY KEY,
+                path BLOB PRIMARY KEY,
+                display_path TEXT NOT NULL,
                 name TEXT NOT NULL,
                 file_type TEXT NOT NULL,
                 size INTEGER NOT NULL,
                 modified INTEGER NOT NULL,
-                parent TEXT
+                parent BLOB,
+                ignored INTEGER NOT NULL DEFAULT 0,
+                link_target BLOB,
+                link_target_type TEXT,
+                mode INTEGER NOT NULL DEFAULT 0,
+                uid INTEGER NOT NULL DEFAULT 0,
+                gid INTEGER NOT NULL DEFAULT 0,
+                owner TEXT,
+                group_name TEXT,
+                created INTEGER,
+                accessed INTEGER NOT NULL DEFAULT 0,
+                device INTEGER NOT NULL DEFAULT 0,
+                inode INTEGER NOT NULL DEFAULT 0,
+                nlink INTEGER NOT NULL DEFAULT 0,
+                flags INTEGER NOT NULL DEFAULT 0,
+                extension TEXT,
+                mime TEXT,
+                kind TEXT
             )",
             [],
         )?;
@@ -64,7 +386,7 @@ impl TagDatabase {
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS file_tags (
-                file_path TEXT NOT NULL,
+                file_path BLOB NOT NULL,
                 tag_name TEXT NOT NULL,
                 PRIMARY KEY (file_path, tag_name),
                 FOREIGN KEY (file_path) REFERENCES files(path) ON DELETE CASCADE,
@@ -83,74 +405,223 @@ impl TagDatabase {
             [],
         )?;
 
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_files_extension ON files(extension)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_files_kind ON files(kind)",
+            [],
+        )?;
+
         if let Err(e) = conn.execute(
             "CREATE VIRTUAL TABLE IF NOT EXISTS files_fts USING fts5(
                 name,
-                path,
+                display_path,
                 content='files',
                 content_rowid='rowid'
             )",
             [],
         ) {
-            eprintln!("Failed to create files_fts virtual table: {}", e);
+            notifications::error!(format!("Full-text search is unavailable: {}", e), None);
         }
 
-        Ok(TagDatabase {
-            conn: Arc::new(Mutex::new(conn)),
-        })
-    }
-
-    pub fn insert_file(&self, entry: &FileEntry) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT OR REPLACE INTO files (path, name, file_type, size, modified, parent)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![
-                normalize_path(&entry.path),
-                entry.name,
-                match entry.file_type {
-                    FileType::File => "file",
-                    FileType::Directory => "directory",
-                },
-                entry.size,
-                entry.modified,
-                entry.parent.as_ref().map(|p| normalize_path(p))
-            ],
+            "ATTACH DATABASE ?1 AS usage",
+            params![usage_path.to_string_lossy()],
         )?;
 
-        let normalized_path = normalize_path(&entry.path);
         conn.execute(
-            "INSERT INTO files_fts (rowid, name, path) VALUES (
-                (SELECT rowid FROM files WHERE path = ?1),
-                ?2,
-                ?3
-            ) ON CONFLICT(rowid) DO UPDATE SET name = ?2, path = ?3",
-            params![
-                normalized_path,
-                entry.name,
-                normalized_path
-            ],
+            "CREATE TABLE IF NOT EXISTS usage.visits (
+                path BLOB NOT NULL,
+                kind TEXT NOT NULL,
+                visited_at INTEGER NOT NULL
+            )",
+            [],
         )?;
 
-        Ok(())
-    }
-
-    pub fn add_tag_to_file(&self, file_path: &PathBuf, tag_name: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        
         conn.execute(
-            "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
-            params![tag_name],
+            "CREATE INDEX IF NOT EXISTS usage.idx_visits_path ON visits(path)",
+            [],
         )?;
 
         conn.execute(
-            "INSERT OR IGNORE INTO file_tags (file_path, tag_name) VALUES (?1, ?2)",
-            params![normalize_path(file_path), tag_name],
+            "CREATE TABLE IF NOT EXISTS frecency (
+                path BLOB PRIMARY KEY,
+                score INTEGER NOT NULL,
+                last_visited INTEGER NOT NULL
+            )",
+            [],
         )?;
 
+        refresh_frecency(&conn)?;
+
+        Ok(TagDatabase {
+            conn: Arc::new(Mutex::new(conn)),
+            frecency_refreshed: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Recomputes every score once `FRECENCY_REFRESH_INTERVAL` has passed
+    /// since the last time. Called before reading scores.
+    pub(crate) fn refresh_stale_frecency(&self, conn: &Connection) -> Result<()> {
+        let mut refreshed = self.frecency_refreshed.lock().unwrap();
+        if refreshed.elapsed() >= FRECENCY_REFRESH_INTERVAL {
+            refresh_frecency(conn)?;
+            *refreshed = Instant::now();
+        }
         Ok(())
     }
 
+    /// Brings the index in line with a batch of directory listings in a
+    /// single transaction: new and changed entries (by size, mtime, type or
+    /// ignore flag) are written, unchanged ones are skipped, children missing
+    /// from a complete listing are removed with everything below them, and
+    /// each entry's tag rows are replaced when its tags differ.
+    pub fn sync_directories(&self, scans: &[DirectoryScan]) -> Result<SyncStats> {
+        let mut stats = SyncStats::default();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut existing_children = tx.prepare_cached(&format!(
+                "SELECT path, {} FROM files WHERE parent = ?1",
+                RowState::COLUMNS
+            ))?;
+            let mut existing_tags = tx.prepare_cached(
+                "SELECT ft.file_path, ft.tag_name
+                 FROM file_tags ft
+                 INNER JOIN files f ON f.path = ft.file_path
+                 WHERE f.parent = ?1",
+            )?;
+            let mut upsert_file = tx.prepare_cached(
+                "INSERT INTO files (path, name, file_type, size, modified, parent, ignored,
+                                    link_target, link_target_type, mode, uid, gid, owner, group_name,
+                                    created, accessed, device, inode, nlink, extension, mime, kind, display_path, flags)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+                         ?21, ?22, ?23, ?24)
+                 ON CONFLICT(path) DO UPDATE SET
+                    name = excluded.name,
+                    file_type = excluded.file_type,
+                    size = excluded.size,
+                    modified = excluded.modified,
+                    parent = excluded.parent,
+                    ignored = excluded.ignored,
+                    link_target = excluded.link_target,
+                    link_target_type = excluded.link_target_type,
+                    mode = excluded.mode,
+                    uid = excluded.uid,
+                    gid = excluded.gid,
+                    owner = excluded.owner,
+                    group_name = excluded.group_name,
+                    created = excluded.created,
+                    accessed = excluded.accessed,
+                    device = excluded.device,
+                    inode = excluded.inode,
+                    nlink = excluded.nlink,
+                    extension = excluded.extension,
+                    mime = excluded.mime,
+                    kind = excluded.kind,
+                    flags = excluded.flags",
+            )?;
+            // Name and path never change for an existing row, so the
+            // full-text entry only needs writing when the row is new.
+            let mut insert_fts = tx.prepare_cached(
+                "INSERT OR REPLACE INTO files_fts (rowid, name, display_path)
+                 VALUES ((SELECT rowid FROM files WHERE path = ?1), ?2, ?3)",
+            )?;
+            let mut delete_file_tags = tx.prepare_cached("DELETE FROM file_tags WHERE file_path = ?1")?;
+            let mut insert_tag = tx.prepare_cached("INSERT OR IGNORE INTO tags (name) VALUES (?1)")?;
+            let mut insert_file_tag = tx.prepare_cached(
+                "INSERT OR IGNORE INTO file_tags (file_path, tag_name) VALUES (?1, ?2)",
+            )?;
+
+            for scan in scans {
+                let dir = normalize_path(&scan.dir);
+                let mut existing: HashMap<Vec<u8>, RowState> = existing_children
+                    .query_map(params![dir], |row| Ok((row.get(0)?, RowState::from_row(row, 1)?)))?
+                    .collect::<Result<_>>()?;
+                let mut tags_by_path: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
+                for row in existing_tags.query_map(params![dir], |row| Ok((row.get(0)?, row.get(1)?)))? {
+                    let (path, tag): (Vec<u8>, String) = row?;
+                    tags_by_path.entry(path).or_default().push(tag);
+                }
+
+                for file in &scan.entries {
+                    let entry = &file.entry;
+                    let path = normalize_path(&entry.path);
+                    let current = RowState::of(entry);
+                    let metadata = &entry.metadata;
+
+                    match existing.remove(&path) {
+                        Some(previous) if previous == current => stats.unchanged += 1,
+                        previous => {
+                            upsert_file.execute(params![
+                                path,
+                                entry.name,
+                                current.file_type,
+                                entry.size,
+                                entry.modified,
+                                entry.parent.as_deref().map(normalize_path),
+                                entry.ignored,
+                                current.link_target,
+                                current.link_target_type,
+                                metadata.mode,
+                                metadata.uid,
+                                metadata.gid,
+                                metadata.owner,
+                                metadata.group,
+                                metadata.created,
+                                metadata.accessed,
+                                metadata.device as i64,
+                                metadata.inode as i64,
+                                metadata.nlink,
+                                metadata.extension,
+                                metadata.mime,
+                                metadata.kind.map(FileKind::name),
+                                entry.path.to_string_lossy(),
+                                metadata.flags
+                            ])?;
+                            if previous.is_none() {
+                                insert_fts.execute(params![path, entry.name, entry.path.to_string_lossy()])?;
+                            }
+                            stats.written += 1;
+                        }
+                    }
+
+                    let mut previous_tags = tags_by_path.remove(&path).unwrap_or_default();
+                    let mut tags = file.tags.clone();
+                    previous_tags.sort();
+                    tags.sort();
+                    tags.dedup();
+                    if previous_tags != tags {
+                        delete_file_tags.execute(params![path])?;
+                        for tag in &tags {
+                            insert_tag.execute(params![tag])?;
+                            insert_file_tag.execute(params![path, tag])?;
+                        }
+                        stats.tags_changed = true;
+                    }
+                }
+
+                if scan.complete {
+                    for path in existing.keys() {
+                        stats.removed += remove_subtree(&tx, path)?;
+                    }
+                }
+            }
+
+            if stats.removed > 0 || stats.tags_changed {
+                tx.execute(
+                    "DELETE FROM tags WHERE name NOT IN (SELECT tag_name FROM file_tags)",
+                    [],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(stats)
+    }
+
     pub fn get_all_tags(&self) -> Result<Vec<Tag>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
@@ -176,78 +647,130 @@ impl TagDatabase {
     pub fn get_files_by_tag(&self, tag_name: &str) -> Result<Vec<FileEntry>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT f.path, f.name, f.file_type, f.size, f.modified, f.parent
+            &format!(
+            "SELECT {}
              FROM files f
              INNER JOIN file_tags ft ON f.path = ft.file_path
              WHERE ft.tag_name = ?1
-             ORDER BY f.name"
-        )?;
+             ORDER BY f.name",
+            FILE_COLUMNS
+        ))?;
 
-        let files = stmt.query_map(params![tag_name], |row| {
-            Ok(FileEntry {
-                path: PathBuf::from(row.get::<_, String>(0)?),
-                name: row.get(1)?,
-                file_type: match row.get::<_, String>(2)?.as_str() {
-                    "file" => FileType::File,
-                    "directory" => FileType::Directory,
-                    _ => FileType::File,
-                },
-                size: row.get(3)?,
-                modified: row.get(4)?,
-                parent: row.get::<_, Option<String>>(5)?.map(PathBuf::from),
-            })
-        })?
+        let files = stmt.query_map(params![tag_name], file_entry_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
 
         Ok(files)
     }
 
-    pub fn get_files_in_directory(&self, dir_path: &PathBuf) -> Result<Vec<FileEntry>> {
+    /// Tags of the file at `path`, as last indexed.
+    pub fn get_tags_for_file(&self, path: &Path) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT tag_name FROM file_tags WHERE file_path = ?1 ORDER BY tag_name")?;
+
+        let tags = stmt.query_map(params![normalize_path(path)], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(tags)
+    }
+
+    /// Number of indexed files of each kind, skipping kinds with none.
+    pub fn get_kind_counts(&self) -> Result<Vec<(FileKind, usize)>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT path, name, file_type, size, modified, parent
-             FROM files
-             WHERE parent = ?1
-             ORDER BY file_type DESC, name"
+            "SELECT kind, COUNT(*) FROM files WHERE kind IS NOT NULL GROUP BY kind"
         )?;
 
-        let files = stmt.query_map(params![normalize_path(dir_path)], |row| {
-            Ok(FileEntry {
-                path: PathBuf::from(row.get::<_, String>(0)?),
-                name: row.get(1)?,
-                file_type: match row.get::<_, String>(2)?.as_str() {
-                    "file" => FileType::File,
-                    "directory" => FileType::Directory,
-                    _ => FileType::File,
-                },
-                size: row.get(3)?,
-                modified: row.get(4)?,
-                parent: row.get::<_, Option<String>>(5)?.map(PathBuf::from),
-            })
-        })?
+        let counts: HashMap<String, usize> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_>>()?;
+
+        Ok(FileKind::ALL
+            .into_iter()
+            .filter_map(|kind| counts.get(kind.name()).map(|&count| (kind, count)))
+            .collect())
+    }
+
+    pub fn get_files_by_kind(&self, kind: FileKind) -> Result<Vec<FileEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM files f
+             WHERE f.kind = ?1
+             ORDER BY f.name
+             LIMIT 5000",
+            FILE_COLUMNS
+        ))?;
+
+        let files = stmt.query_map(params![kind.name()], file_entry_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
 
         Ok(files)
     }
 
-    pub fn get_directory(&self, dir_path: &PathBuf) -> Result<Option<FileEntry>> {
+    pub fn get_files_in_directory(&self, dir_path: &Path) -> Result<Vec<FileEntry>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT path, name, file_type, size, modified, parent
+            &format!(
+            "SELECT {}
+             FROM files f
+             WHERE f.parent = ?1
+             ORDER BY f.file_type DESC, f.name",
+            FILE_COLUMNS
+        ))?;
+
+        let files = stmt.query_map(params![normalize_path(dir_path)], file_entry_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(files)
+    }
+
+    /// True when anything inside `dir_path` is indexed; for an archive, that
+    /// its contents were.
+    pub fn has_children(&self, dir_path: &Path) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM files WHERE parent = ?1)",
+            params![normalize_path(dir_path)],
+            |row| row.get(0),
+        )
+    }
+
+    /// How the indexed files directly inside `dir_path` were classified, so
+    /// a re-scan only reads the contents of files that changed.
+    pub fn get_classifications(&self, dir_path: &Path) -> Result<HashMap<PathBuf, Classified>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT path, size, modified, mime, kind
              FROM files
-             WHERE path = ?1 AND file_type = 'directory'"
+             WHERE parent = ?1 AND kind IS NOT NULL",
         )?;
 
-        let mut entries = stmt.query_map(params![normalize_path(dir_path)], |row| {
-            Ok(FileEntry {
-                path: PathBuf::from(row.get::<_, String>(0)?),
-                name: row.get(1)?,
-                file_type: FileType::Directory,
-                size: row.get(3)?,
-                modified: row.get(4)?,
-                parent: row.get::<_, Option<String>>(5)?.map(PathBuf::from),
-            })
-        })?;
+        let classifications = stmt.query_map(params![normalize_path(dir_path)], |row| {
+            Ok((
+                os_path::from_bytes(row.get(0)?),
+                Classified {
+                    size: row.get(1)?,
+                    modified: row.get(2)?,
+                    mime: row.get(3)?,
+                    kind: row.get::<_, Option<String>>(4)?.as_deref().and_then(FileKind::from_name),
+                },
+            ))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(classifications)
+    }
+
+    pub fn get_directory(&self, dir_path: &Path) -> Result<Option<FileEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            &format!(
+            "SELECT {}
+             FROM files f
+             WHERE f.path = ?1 AND f.file_type = 'directory'",
+            FILE_COLUMNS
+        ))?;
+
+        let mut entries = stmt.query_map(params![normalize_path(dir_path)], file_entry_from_row)?;
 
         if let Some(entry) = entries.next() {
             Ok(Some(entry?))
@@ -255,4 +778,196 @@ impl TagDatabase {
             Ok(None)
         }
     }
+
+    pub fn record_visit(&self, path: &Path, kind: VisitKind) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO usage.visits (path, kind, visited_at)
+             VALUES (?1, ?2, CAST(strftime('%s', 'now') AS INTEGER))",
+            params![
+                normalize_path(path),
+                match kind {
+                    VisitKind::Opened => "opened",
+                    VisitKind::Entered => "entered",
+                },
+            ],
+        )?;
+
+        conn.execute(
+            &format!("INSERT OR REPLACE INTO frecency {} WHERE path = ?1 GROUP BY path", FRECENCY_SQL),
+            params![normalize_path(path)],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_frecency_scores(&self) -> Result<HashMap<PathBuf, i64>> {
+        let conn = self.conn.lock().unwrap();
+        self.refresh_stale_frecency(&conn)?;
+        let mut stmt = conn.prepare("SELECT path, score FROM frecency")?;
+
+        let scores = stmt.query_map([], |row| {
+            Ok((os_path::from_bytes(row.get(0)?), row.get(1)?))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(scores)
+    }
+
+    /// Indexed files and folders that were opened or entered, most recent first.
+    pub fn get_recent_files(&self, limit: usize) -> Result<Vec<FileEntry>> {
+        let conn = self.conn.lock().unwrap();
+        self.refresh_stale_frecency(&conn)?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM files f
+             INNER JOIN frecency fr ON fr.path = f.path
+             ORDER BY fr.last_visited DESC
+             LIMIT ?1",
+            FILE_COLUMNS
+        ))?;
+
+        let files = stmt.query_map(params![limit as i64], file_entry_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(files)
+    }
+}
+
+/// Forgets visits past the retention period and recomputes every score.
+fn refresh_frecency(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "DELETE FROM usage.visits
+         WHERE visited_at < CAST(strftime('%s', 'now') AS INTEGER) - ?1",
+        params![VISIT_RETENTION_SECS],
+    )?;
+    conn.execute("DELETE FROM frecency", [])?;
+    conn.execute(&format!("INSERT INTO frecency {} GROUP BY path", FRECENCY_SQL), [])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexed(path: &str, file_type: FileType, tags: &[&str]) -> IndexedFile {
+        let path = PathBuf::from(path);
+        IndexedFile {
+            entry: FileEntry {
+                name: path.file_name().unwrap().to_string_lossy().to_string(),
+                parent: path.parent().map(Path::to_path_buf),
+                path,
+                file_type,
+                size: 10,
+                modified: 1_700_000_000,
+                ignored: false,
+                link: None,
+                metadata: FileMetadata {
+                    device: u64::MAX,
+                    inode: u64::MAX - 1,
+                    ..FileMetadata::default()
+                },
+            },
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    fn scan(dir: &str, entries: Vec<IndexedFile>, complete: bool) -> DirectoryScan {
+        DirectoryScan {
+            dir: PathBuf::from(dir),
+            entries,
+            complete,
+        }
+    }
+
+    fn names(db: &TagDatabase, dir: &str) -> Vec<String> {
+        let mut names: Vec<String> =
+            db.get_files_in_directory(Path::new(dir)).unwrap().into_iter().map(|f| f.name).collect();
+        names.sort();
+        names
+    }
+
+    fn index_tree(db: &TagDatabase) {
+        db.sync_directories(&[
+            scan(
+                "/r",
+                vec![indexed("/r/a.txt", FileType::File, &["work"]), indexed("/r/sub", FileType::Directory, &[])],
+                true,
+            ),
+            scan("/r/sub", vec![indexed("/r/sub/c.txt", FileType::File, &["work"])], true),
+        ])
+        .unwrap();
+    }
+
+    #[test]
+    fn unchanged_entries_are_not_rewritten() {
+        let db = TagDatabase::new().unwrap();
+        index_tree(&db);
+        let stats = db
+            .sync_directories(&[scan("/r/sub", vec![indexed("/r/sub/c.txt", FileType::File, &["work"])], true)])
+            .unwrap();
+        assert_eq!((stats.written, stats.unchanged, stats.removed), (0, 1, 0));
+
+        let file = db.get_files_in_directory(Path::new("/r/sub")).unwrap().remove(0);
+        assert_eq!((file.metadata.device, file.metadata.inode), (u64::MAX, u64::MAX - 1));
+    }
+
+    #[test]
+    fn complete_scan_prunes_missing_entries_with_their_subtree_and_tags() {
+        let db = TagDatabase::new().unwrap();
+        index_tree(&db);
+        let stats = db
+            .sync_directories(&[scan("/r", vec![indexed("/r/a.txt", FileType::File, &[])], true)])
+            .unwrap();
+
+        assert_eq!(stats.removed, 2);
+        assert_eq!(names(&db, "/r"), ["a.txt"]);
+        assert!(names(&db, "/r/sub").is_empty());
+        assert!(db.get_tags_for_file(Path::new("/r/a.txt")).unwrap().is_empty());
+        assert!(db.get_all_tags().unwrap().is_empty());
+    }
+
+    #[test]
+    fn incomplete_scan_keeps_missing_entries() {
+        let db = TagDatabase::new().unwrap();
+        index_tree(&db);
+        let stats = db
+            .sync_directories(&[scan("/r", vec![indexed("/r/a.txt", FileType::File, &["work"])], false)])
+            .unwrap();
+
+        assert_eq!(stats.removed, 0);
+        assert_eq!(names(&db, "/r"), ["a.txt", "sub"]);
+        assert_eq!(names(&db, "/r/sub"), ["c.txt"]);
+    }
+
+    #[test]
+    fn pruning_does_not_touch_siblings_sharing_a_prefix() {
+        let db = TagDatabase::new().unwrap();
+        db.sync_directories(&[
+            scan(
+                "/r",
+                vec![indexed("/r/sub", FileType::Directory, &[]), indexed("/r/sub2", FileType::Directory, &[])],
+                true,
+            ),
+            scan("/r/sub2", vec![indexed("/r/sub2/d.txt", FileType::File, &[])], true),
+        ])
+        .unwrap();
+        db.sync_directories(&[scan("/r", vec![indexed("/r/sub2", FileType::Directory, &[])], true)]).unwrap();
+
+        assert_eq!(names(&db, "/r"), ["sub2"]);
+        assert_eq!(names(&db, "/r/sub2"), ["d.txt"]);
+    }
+
+    #[test]
+    fn a_full_refresh_ages_scores_that_no_visit_touched() {
+        let db = TagDatabase::new().unwrap();
+        db.record_visit(Path::new("/a"), VisitKind::Opened).unwrap();
+        assert_eq!(db.get_frecency_scores().unwrap()[Path::new("/a")], 100);
+
+        let conn = db.conn.lock().unwrap();
+        conn.execute("UPDATE usage.visits SET visited_at = visited_at - 20 * 86400", []).unwrap();
+        refresh_frecency(&conn).unwrap();
+        drop(conn);
+        assert_eq!(db.get_frecency_scores().unwrap()[Path::new("/a")], 50);
+    }
 }
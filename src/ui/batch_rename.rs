@@ -0,0 +1,196 @@
+use eframe::egui;
+use std::path::Path;
+
+use crate::batch_rename::{self, CaseChange, ExtensionChange, PlannedRename, RenameRule, Renamer};
+use crate::tag_db::FileEntry;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExtensionMode {
+    Keep,
+    Replace,
+    Remove,
+}
+
+/// The rule being edited and the preview it produced. The preview is only
+/// recomputed when the rule changes, since it checks every target on disk.
+pub struct BatchRenameDraft {
+    files: Vec<FileEntry>,
+    rule: RenameRule,
+    extension_mode: ExtensionMode,
+    new_extension: String,
+    preview: Result<Vec<PlannedRename>, String>,
+    previewed: Option<RenameRule>,
+}
+
+impl BatchRenameDraft {
+    pub fn new(files: Vec<FileEntry>) -> Self {
+        BatchRenameDraft {
+            files,
+            rule: RenameRule::default(),
+            extension_mode: ExtensionMode::Keep,
+            new_extension: String::new(),
+            preview: Ok(Vec::new()),
+            previewed: None,
+        }
+    }
+
+    fn refresh_preview(&mut self, exists: &dyn Fn(&Path) -> bool) {
+        self.rule.extension = match self.extension_mode {
+            ExtensionMode::Keep => ExtensionChange::Keep,
+            ExtensionMode::Replace => ExtensionChange::Replace(self.new_extension.trim().to_string()),
+            ExtensionMode::Remove => ExtensionChange::Remove,
+        };
+        if self.previewed.as_ref() == Some(&self.rule) {
+            return;
+        }
+        self.preview = Renamer::new(&self.rule).map(|renamer| batch_rename::plan(&self.files, &renamer, exists));
+        self.previewed = Some(self.rule.clone());
+    }
+}
+
+pub enum BatchRenameAction {
+    Apply(Vec<PlannedRename>),
+    Cancel,
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// The rule editor above a before/after preview of every name.
+pub fn render_batch_rename_window(
+    ctx: &egui::Context,
+    draft: &mut BatchRenameDraft,
+    exists: &dyn Fn(&Path) -> bool,
+) -> Option<BatchRenameAction> {
+    let mut action = None;
+    let mut open = true;
+    draft.refresh_preview(exists);
+
+    egui::Window::new(format!("Rename {} items", draft.files.len()))
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_width(560.0)
+        .show(ctx, |ui| {
+            egui::Grid::new("batch_rename_rule")
+                .num_columns(2)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label("Find:");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut draft.rule.find).desired_width(220.0));
+                        ui.checkbox(&mut draft.rule.regex, "Regular expression");
+                    });
+                    ui.end_row();
+
+                    ui.label("Replace:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.rule.replace)
+                            .hint_text(if draft.rule.regex { "$1 inserts a capture group" } else { "" })
+                            .desired_width(220.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Case:");
+                    egui::ComboBox::from_id_source("batch_rename_case")
+                        .selected_text(draft.rule.case.label())
+                        .show_ui(ui, |ui| {
+                            for case in CaseChange::ALL {
+                                ui.selectable_value(&mut draft.rule.case, case, case.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Name:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.rule.template)
+                            .hint_text("{name}")
+                            .desired_width(300.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("");
+                    ui.label(
+                        egui::RichText::new("{name}  {n}  {n:03}  {date}  {date:%Y%m%d}  {ext}").weak().monospace(),
+                    );
+                    ui.end_row();
+
+                    ui.label("Counter from:");
+                    ui.add(egui::DragValue::new(&mut draft.rule.counter_start));
+                    ui.end_row();
+
+                    ui.label("Extension:");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut draft.extension_mode, ExtensionMode::Keep, "Keep");
+                        ui.radio_value(&mut draft.extension_mode, ExtensionMode::Replace, "Change to");
+                        ui.add_enabled(
+                            draft.extension_mode == ExtensionMode::Replace,
+                            egui::TextEdit::singleline(&mut draft.new_extension).desired_width(60.0),
+                        );
+                        ui.radio_value(&mut draft.extension_mode, ExtensionMode::Remove, "Remove");
+                    });
+                    ui.end_row();
+                });
+            ui.separator();
+
+            let mut can_apply = false;
+            match &draft.preview {
+                Err(e) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                Ok(plan) => {
+                    egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        egui::Grid::new("batch_rename_preview")
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for rename in plan {
+                                    ui.label(file_name(&rename.from));
+                                    ui.label("→");
+                                    let new_name = file_name(&rename.to);
+                                    match &rename.problem {
+                                        Some(problem) => {
+                                            ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", new_name))
+                                                .on_hover_text(problem.describe());
+                                        }
+                                        None if !rename.changes() => {
+                                            ui.label(egui::RichText::new(new_name).weak());
+                                        }
+                                        None => {
+                                            ui.label(new_name);
+                                        }
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                    let changes = plan.iter().filter(|rename| rename.changes()).count();
+                    let problems = plan.iter().filter(|rename| rename.problem.is_some()).count();
+                    let summary = match problems {
+                        0 => format!("{} of {} will be renamed", changes, plan.len()),
+                        _ => format!("{} problems to fix before renaming", problems),
+                    };
+                    ui.label(egui::RichText::new(summary).weak());
+                    can_apply = changes > 0 && problems == 0;
+                }
+            }
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(can_apply, egui::Button::new("Rename")).clicked() {
+                    if let Ok(plan) = &draft.preview {
+                        action = Some(BatchRenameAction::Apply(plan.clone()));
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    action = Some(BatchRenameAction::Cancel);
+                }
+            });
+        });
+
+    if !open {
+        action = Some(BatchRenameAction::Cancel);
+    }
+    action
+}
@@ -0,0 +1,167 @@
+use eframe::egui;
+
+use crate::keymap::Action;
+use crate::ui::theme;
+
+const MAX_VISIBLE: usize = 12;
+
+/// What a palette entry does when chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteCommand {
+    Run(Action),
+    OpenBookmark(usize),
+    FilterByTag(String),
+    OpenWith(String),
+}
+
+pub struct PaletteEntry {
+    pub label: String,
+    /// Where the entry applies, e.g. "Folders view" or "Favorites".
+    pub context: String,
+    /// Current key bindings, already formatted; empty when unbound.
+    pub keys: String,
+    pub command: PaletteCommand,
+}
+
+pub enum PaletteResult {
+    Execute(PaletteCommand),
+    Close,
+}
+
+#[derive(Default)]
+pub struct CommandPalette {
+    query: String,
+    selected: usize,
+}
+
+/// Scores `text` against `query` as a case-insensitive subsequence match.
+/// Consecutive characters and matches at word starts score higher; `None`
+/// means some query character was not found.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for wanted in query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        let found = text[position..].iter().position(|&c| c == wanted)? + position;
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - position).min(10) as i32 / 2;
+        previous_match = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// Shows the palette over everything else. Up/Down move the selection,
+/// Enter runs it and Escape closes the palette.
+pub fn render_command_palette(
+    ctx: &egui::Context,
+    palette: &mut CommandPalette,
+    entries: &[PaletteEntry],
+) -> Option<PaletteResult> {
+    let mut result = None;
+    let theme = theme::current(ctx);
+
+    let mut matches: Vec<(i32, &PaletteEntry)> = entries
+        .iter()
+        .filter_map(|entry| {
+            let haystack = format!("{} {}", entry.label, entry.context);
+            fuzzy_score(&palette.query, &haystack).map(|score| (score, entry))
+        })
+        .collect();
+    matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+
+    let (up, down, enter, escape) = ctx.input(|i| {
+        (
+            i.key_pressed(egui::Key::ArrowUp),
+            i.key_pressed(egui::Key::ArrowDown),
+            i.key_pressed(egui::Key::Enter),
+            i.key_pressed(egui::Key::Escape),
+        )
+    });
+    if up {
+        palette.selected = palette.selected.saturating_sub(1);
+    }
+    if down && palette.selected + 1 < matches.len() {
+        palette.selected += 1;
+    }
+
+    egui::Window::new("Command palette")
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .fixed_size(egui::vec2(520.0, 0.0))
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 80.0))
+        .show(ctx, |ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut palette.query)
+                    .hint_text("Type a command…")
+                    .desired_width(f32::INFINITY),
+            );
+            if !response.has_focus() {
+                response.request_focus();
+            }
+            if response.changed() {
+                palette.selected = 0;
+            }
+            ui.separator();
+
+            if matches.is_empty() {
+                ui.label(egui::RichText::new("No matching commands").color(theme.text_secondary));
+            }
+
+            let first = palette.selected.saturating_sub(MAX_VISIBLE - 1);
+            for (index, (_, entry)) in matches.iter().enumerate().skip(first).take(MAX_VISIBLE) {
+                let is_selected = index == palette.selected;
+                let row = ui.horizontal(|ui| {
+                    let label = ui.selectable_label(is_selected, &entry.label);
+                    ui.label(egui::RichText::new(&entry.context).size(11.0).color(theme.text_secondary));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.monospace(&entry.keys);
+                    });
+                    label
+                });
+                if row.inner.clicked() {
+                    result = Some(PaletteResult::Execute(entry.command.clone()));
+                }
+            }
+        });
+
+    if enter {
+        if let Some((_, entry)) = matches.get(palette.selected) {
+            result = Some(PaletteResult::Execute(entry.command.clone()));
+        }
+    }
+    if escape {
+        result = Some(PaletteResult::Close);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score_matches_subsequences_only() {
+        assert!(fuzzy_score("stg", "Open settings").is_some());
+        assert_eq!(fuzzy_score("xyz", "Open settings"), None);
+        assert!(fuzzy_score("", "anything").is_some());
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_word_starts_and_runs() {
+        let run = fuzzy_score("set", "Open settings").unwrap();
+        let scattered = fuzzy_score("set", "Show recent tabs").unwrap();
+        assert!(run > scattered);
+    }
+}
@@ -0,0 +1,80 @@
+use eframe::egui;
+
+use crate::bookmarks::Bookmarks;
+
+pub enum FavoriteAction {
+    Open(usize),
+    StartRename(usize),
+    Rename(usize, String),
+    CancelRename,
+    Move(usize, usize),
+    Remove(usize),
+}
+
+/// `renaming` holds the index and in-progress name of an entry being renamed.
+pub fn render_favorites(
+    ui: &mut egui::Ui,
+    bookmarks: &Bookmarks,
+    renaming: &mut Option<(usize, String)>,
+) -> Option<FavoriteAction> {
+    let mut action = None;
+    let count = bookmarks.entries().len();
+
+    ui.heading("Favorites");
+    ui.separator();
+
+    if count == 0 {
+        ui.label(egui::RichText::new("Use ☆ to pin a location").small().weak());
+    }
+
+    for (index, bookmark) in bookmarks.entries().iter().enumerate() {
+        if let Some((rename_index, name)) = renaming {
+            if *rename_index == index {
+                let response = ui.add(egui::TextEdit::singleline(name).desired_width(130.0));
+                if !response.has_focus() && !response.lost_focus() {
+                    response.request_focus();
+                }
+                if response.lost_focus() {
+                    action = if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        Some(FavoriteAction::CancelRename)
+                    } else {
+                        Some(FavoriteAction::Rename(index, name.clone()))
+                    };
+                }
+                continue;
+            }
+        }
+
+        let mut label = format!("{} {}", bookmark.icon(), bookmark.name);
+        if index < 9 {
+            label = format!("{}  ⌃⇧{}", label, index + 1);
+        }
+        let response = ui.selectable_label(false, label);
+        if response.clicked() {
+            action = Some(FavoriteAction::Open(index));
+        }
+        if response.double_clicked() {
+            action = Some(FavoriteAction::StartRename(index));
+        }
+        response.context_menu(|ui| {
+            if ui.button("Rename").clicked() {
+                action = Some(FavoriteAction::StartRename(index));
+                ui.close_menu();
+            }
+            if ui.add_enabled(index > 0, egui::Button::new("Move up")).clicked() {
+                action = Some(FavoriteAction::Move(index, index - 1));
+                ui.close_menu();
+            }
+            if ui.add_enabled(index + 1 < count, egui::Button::new("Move down")).clicked() {
+                action = Some(FavoriteAction::Move(index, index + 1));
+                ui.close_menu();
+            }
+            if ui.button("Remove").clicked() {
+                action = Some(FavoriteAction::Remove(index));
+                ui.close_menu();
+            }
+        });
+    }
+
+    action
+}
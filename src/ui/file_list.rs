@@ -1,22 +1,125 @@
 use eframe::egui;
 use std::path::PathBuf;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
 
+use crate::archive;
 use crate::file_associations::FileAssociations;
+use crate::file_kind::FileKind;
+use crate::notifications;
 use crate::tag_db::FileEntry;
 use crate::ui::theme;
+use crate::vfs::trash;
 
 const ROW_HEIGHT: f32 = 65.0;
 const BUFFER_ITEMS: usize = 5;
 
+/// Vertical scroll position of a file list, kept per tab.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ListScroll {
+    pub offset: f32,
+    #[serde(skip)]
+    pub pending_restore: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SortKey {
+    /// The order the list was produced in (folder order, search relevance, ...).
+    #[default]
+    Default,
+    Name,
+    Size,
+    Modified,
+    Created,
+    Accessed,
+    Extension,
+    Owner,
+    Permissions,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 9] = [
+        SortKey::Default,
+        SortKey::Name,
+        SortKey::Size,
+        SortKey::Modified,
+        SortKey::Created,
+        SortKey::Accessed,
+        SortKey::Extension,
+        SortKey::Owner,
+        SortKey::Permissions,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Default => "Default",
+            SortKey::Name => "Name",
+            SortKey::Size => "Size",
+            SortKey::Modified => "Modified",
+            SortKey::Created => "Created",
+            SortKey::Accessed => "Accessed",
+            SortKey::Extension => "Extension",
+            SortKey::Owner => "Owner",
+            SortKey::Permissions => "Permissions",
+        }
+    }
+}
+
+/// How a file list is sorted, kept per tab. Folders always come first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl SortOrder {
+    pub fn apply(&self, files: &mut [FileEntry]) {
+        if self.key == SortKey::Default {
+            return;
+        }
+
+        files.sort_by(|a, b| {
+            let ordering = match self.key {
+                SortKey::Default => std::cmp::Ordering::Equal,
+                SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortKey::Size => a.size.cmp(&b.size),
+                SortKey::Modified => a.modified.cmp(&b.modified),
+                SortKey::Created => a.metadata.created.cmp(&b.metadata.created),
+                SortKey::Accessed => a.metadata.accessed.cmp(&b.metadata.accessed),
+                SortKey::Extension => a.metadata.extension.cmp(&b.metadata.extension),
+                SortKey::Owner => a.metadata.owner.cmp(&b.metadata.owner),
+                SortKey::Permissions => (a.metadata.mode & 0o7777).cmp(&(b.metadata.mode & 0o7777)),
+            };
+            let ordering = if self.descending { ordering.reverse() } else { ordering };
+            b.is_dir().cmp(&a.is_dir()).then(ordering)
+        });
+    }
+}
+
+pub enum FileListAction {
+    ShowProperties(FileEntry),
+    /// Copy a file out of the archive it is in, next to the archive.
+    Extract(FileEntry),
+    /// Cmd-click: add the entry to or drop it from the marked entries.
+    ToggleMark(FileEntry),
+    /// Rename the marked entries, or this one if it is not marked.
+    Rename(FileEntry),
+    /// Ask before deleting a trashed item for good.
+    DeletePermanently(FileEntry),
+}
+
 pub fn render_file_list(
     ui: &mut egui::Ui,
     files: Vec<FileEntry>,
     mut on_dir_click: Option<&mut dyn FnMut(PathBuf)>,
     selected_index: Option<usize>,
+    marked: &BTreeMap<PathBuf, FileEntry>,
+    scroll: &mut ListScroll,
     file_associations: &FileAssociations,
-) {
+) -> Option<FileListAction> {
     let available_size = ui.available_size();
+    let theme = theme::current(ui.ctx());
+    let mut action = None;
     
     if files.is_empty() {
         ui.allocate_ui(available_size, |ui| {
@@ -24,12 +127,17 @@ pub fn render_file_list(
                 ui.label("No files found");
             });
         });
-        return;
+        return None;
     }
 
     ui.allocate_ui(available_size, |ui| {
-        egui::ScrollArea::vertical()
-            .auto_shrink([false, false])
+        let mut scroll_area = egui::ScrollArea::vertical().auto_shrink([false, false]);
+        if scroll.pending_restore {
+            scroll_area = scroll_area.vertical_scroll_offset(scroll.offset);
+            scroll.pending_restore = false;
+        }
+
+        let output = scroll_area
             .show(ui, |ui| {
                 let viewport_height = ui.available_height();
                 let clip_rect = ui.clip_rect();
@@ -52,7 +160,8 @@ pub fn render_file_list(
                 for index in visible_start..visible_end {
                     let file = &files[index];
                     let is_selected = selected_index == Some(index);
-                    let is_dir = matches!(file.file_type, crate::tag_db::FileType::Directory);
+                    let is_marked = marked.contains_key(&file.path);
+                    let is_dir = file.is_dir();
 
                     ui.add_space(4.0);
                     let available_width = ui.available_width();
@@ -66,17 +175,17 @@ pub fn render_file_list(
                         ui.output_mut(|o| o.cursor_icon = egui::CursorIcon::PointingHand);
                     }
 
-                    if is_selected {
+                    if is_selected || is_marked {
                         ui.painter().rect_filled(
                             row_rect,
                             0.0,
-                            theme::ROW_SELECTED_BG,
+                            theme.row_selected_bg,
                         );
                     } else if response.hovered() {
                         ui.painter().rect_filled(
                             row_rect,
                             0.0,
-                            theme::row_hover_bg(),
+                            theme.row_hover_bg,
                         );
                     }
 
@@ -86,12 +195,16 @@ pub fn render_file_list(
                     );
 
                     content_ui.add_space(12.0);
+                    if is_marked {
+                        content_ui.label(egui::RichText::new("✔").color(theme.accent));
+                        content_ui.add_space(4.0);
+                    }
 
-                    let icon_text = if is_dir { "📁" } else { "📄" };
-                    let icon_color = if is_dir {
-                        theme::ICON_DIRECTORY
+                    let (icon_text, icon_color) = if is_dir {
+                        ("📁", theme.icon_directory)
                     } else {
-                        theme::ICON_FILE
+                        let kind = file.metadata.kind.unwrap_or(FileKind::Other);
+                        (kind.icon(), theme.kind_color(kind))
                     };
 
                     content_ui.label(
@@ -104,23 +217,35 @@ pub fn render_file_list(
 
                     content_ui.vertical(|ui| {
                         ui.add_space(4.0);
-                        ui.label(
-                            egui::RichText::new(&file.name)
-                                .size(14.0)
-                                .color(theme::TEXT_PRIMARY),
-                        );
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                egui::RichText::new(&file.name)
+                                    .size(14.0)
+                                    .color(theme.text_primary),
+                            );
+                            if file.has_lossy_name() {
+                                ui.label(egui::RichText::new("�").size(12.0).color(theme.name_lossy))
+                                    .on_hover_text("This name is not valid UTF-8; unreadable bytes are shown as �");
+                            }
+                            if file.is_broken_link() {
+                                ui.label(egui::RichText::new("⚠ broken link").size(11.0).color(theme.link_broken));
+                            } else if file.link.is_some() {
+                                ui.label(egui::RichText::new("🔗").size(12.0).color(theme.link_badge));
+                            }
+                        });
                         ui.add_space(2.0);
 
-                        let path_str = path_string_cache.entry(index).or_insert_with(|| {
-                            file.path.to_string_lossy().to_string()
+                        let path_str = path_string_cache.entry(index).or_insert_with(|| match &file.link {
+                            Some(link) => format!("{} → {}", file.path.to_string_lossy(), link.target.to_string_lossy()),
+                            None => file.path.to_string_lossy().to_string(),
                         });
                         ui.label(
                             egui::RichText::new(path_str.as_str())
                                 .size(11.0)
                                 .color(if is_selected {
-                                    theme::TEXT_SECONDARY_SELECTED
+                                    theme.text_secondary_selected
                                 } else {
-                                    theme::TEXT_SECONDARY
+                                    theme.text_secondary
                                 }),
                         );
                         ui.add_space(4.0);
@@ -148,15 +273,73 @@ pub fn render_file_list(
                         ui.scroll_to_rect(row_rect, Some(egui::Align::Center));
                     }
 
-                    if response.clicked() {
+                    if response.clicked() && ui.input(|i| i.modifiers.command) {
+                        action = Some(FileListAction::ToggleMark(file.clone()));
+                    } else if response.clicked() {
                         if is_dir {
                             if let Some(ref mut on_click) = on_dir_click {
                                 on_click(file.path.clone());
                             }
-                        } else {
+                        } else if let Some(on_click) = on_dir_click.as_mut().filter(|_| archive::is_archive(file)) {
+                            on_click(file.path.clone());
+                        } else if !file.is_broken_link() {
                             let _ = file_associations.open_file(&file.path);
                         }
                     }
+
+                    response.context_menu(|ui| {
+                        // A symlinked folder can be entered through the link
+                        // (clicking) or at the location it resolves to.
+                        if is_dir && file.link.is_some() {
+                            if let Some(ref mut on_click) = on_dir_click {
+                                if ui.button("Open link path").clicked() {
+                                    on_click(file.path.clone());
+                                    ui.close_menu();
+                                }
+                                if ui.button("Open real path").clicked() {
+                                    match std::fs::canonicalize(&file.path) {
+                                        Ok(real_path) => on_click(real_path),
+                                        Err(e) => notifications::error!(format!("Could not resolve link: {}", e), Some(&file.path)),
+                                    }
+                                    ui.close_menu();
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if on_dir_click.is_some() && archive::is_archive(file) {
+                            if ui.button("Open with default app").clicked() {
+                                let _ = file_associations.open_file(&file.path);
+                                ui.close_menu();
+                            }
+                            ui.separator();
+                        }
+                        if !is_dir && archive::is_inside_archive(&file.path) {
+                            if ui.button("Extract next to archive").clicked() {
+                                action = Some(FileListAction::Extract(file.clone()));
+                                ui.close_menu();
+                            }
+                            ui.separator();
+                        }
+                        if trash::contains(&file.path) {
+                            if ui.button("Delete permanently…").clicked() {
+                                action = Some(FileListAction::DeletePermanently(file.clone()));
+                                ui.close_menu();
+                            }
+                            ui.separator();
+                        }
+                        let rename_label = match marked.len() {
+                            count if count > 1 && is_marked => format!("Rename {} marked…", count),
+                            _ => "Rename…".to_string(),
+                        };
+                        if ui.button(rename_label).clicked() {
+                            action = Some(FileListAction::Rename(file.clone()));
+                            ui.close_menu();
+                        }
+                        if ui.button("Properties").clicked() {
+                            action = Some(FileListAction::ShowProperties(file.clone()));
+                            ui.close_menu();
+                        }
+                    });
                 }
                 
                 let remaining_items = files.len().saturating_sub(visible_end);
@@ -164,10 +347,13 @@ pub fn render_file_list(
                     ui.allocate_space(egui::vec2(ui.available_width(), remaining_items as f32 * ROW_HEIGHT));
                 }
             });
+        scroll.offset = output.state.offset.y;
     });
+
+    action
 }
 
-fn format_size(bytes: u64) -> String {
+pub fn format_size(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     let mut size = bytes as f64;
     let mut unit_index = 0;
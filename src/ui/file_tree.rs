@@ -1,24 +1,34 @@
 use eframe::egui;
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::tag_db::{TagDatabase, FileEntry, FileType};
+use crate::hidden::HiddenPolicy;
+use crate::tag_db::{TagDatabase, FileEntry};
 use crate::ui::theme;
 
+/// What every row of the tree is drawn against.
+pub struct TreeContext<'a> {
+    pub tag_db: &'a TagDatabase,
+    pub root_path: &'a Path,
+    /// The folder outlined as the current one.
+    pub current_path: &'a Path,
+    pub show_hidden_files: bool,
+    pub hidden: &'a HiddenPolicy,
+    pub show_ignored_files: bool,
+    pub max_width: f32,
+}
+
 pub fn render_file_tree(
     ui: &mut egui::Ui,
-    tag_db: &TagDatabase,
-    root_path: &PathBuf,
-    current_path: &PathBuf,
+    tree: &TreeContext,
     expanded: &mut HashSet<PathBuf>,
-    show_hidden_files: bool,
     on_path_click: &mut dyn FnMut(PathBuf),
-    max_width: f32,
 ) {
-    let root_entry = match tag_db.get_directory(root_path) {
+    let root_path = tree.root_path;
+    let root_entry = match tree.tag_db.get_directory(root_path) {
         Ok(Some(entry)) => entry,
         Ok(None) => {
-            if *root_path == PathBuf::from("/") {
+            if root_path == Path::new("/") {
                 FileEntry {
                     path: PathBuf::from("/"),
                     name: "/".to_string(),
@@ -26,6 +36,9 @@ pub fn render_file_tree(
                     size: 0,
                     modified: 0,
                     parent: None,
+                    ignored: false,
+                    link: None,
+                    metadata: Default::default(),
                 }
             } else {
                 ui.label("No root directory found");
@@ -38,58 +51,47 @@ pub fn render_file_tree(
         }
     };
 
-    let child_dirs = get_child_directories(tag_db, root_path, show_hidden_files);
     let is_last = true;
     
-    render_directory(
-        ui,
-        tag_db,
-        &root_entry,
-        current_path,
-        expanded,
-        show_hidden_files,
-        on_path_click,
-        0,
-        max_width,
-        is_last,
-        Vec::new(),
-        root_path,
-    );
+    if let Some(path) = render_directory(ui, tree, &root_entry, expanded, 0, is_last, Vec::new()) {
+        on_path_click(path);
+    }
 }
 
+/// Draws `dir` and its expanded descendants; returns the folder clicked, if any.
 fn render_directory(
     ui: &mut egui::Ui,
-    tag_db: &TagDatabase,
+    tree: &TreeContext,
     dir: &FileEntry,
-    current_path: &PathBuf,
     expanded: &mut HashSet<PathBuf>,
-    show_hidden_files: bool,
-    on_path_click: &mut dyn FnMut(PathBuf),
     depth: usize,
-    max_width: f32,
     is_last: bool,
     parent_prefix: Vec<bool>,
-    tree_root: &PathBuf,
-) {
-    if !show_hidden_files && dir.name.starts_with('.') {
-        return;
+) -> Option<PathBuf> {
+    if !tree.show_hidden_files && tree.hidden.is_hidden(dir) {
+        return None;
     }
-    
+
+    if !tree.show_ignored_files && dir.ignored {
+        return None;
+    }
+
     let is_expanded = expanded.contains(&dir.path);
-    let is_current = dir.path == *current_path;
-    let has_children = has_child_directories(tag_db, &dir.path, show_hidden_files);
+    let is_current = dir.path == tree.current_path;
+    let has_children = has_child_directories(tree, &dir.path);
     let child_dirs = if has_children {
-        get_child_directories(tag_db, &dir.path, show_hidden_files)
+        get_child_directories(tree, &dir.path)
     } else {
         vec![]
     };
 
     let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+    let theme = theme::current(ui.ctx());
     
     let mut label_response_opt = None;
     
     ui.allocate_ui(
-        egui::vec2(max_width, row_height),
+        egui::vec2(tree.max_width, row_height),
         |ui| {
             let mut prefix_string = String::new();
             for &is_parent_last in &parent_prefix {
@@ -139,17 +141,20 @@ fn render_directory(
 
                 ui.add_space(2.0);
 
-                let display_name = if dir.path == *tree_root {
+                let display_name = if dir.path == tree.root_path {
                     "/".to_string()
                 } else {
                     dir.name.clone()
                 };
                 
-                let label_text = if display_name.len() > 20 {
-                    format!("{}...", &display_name[..17])
+                let mut label_text = if display_name.chars().count() > 20 {
+                    format!("{}...", display_name.chars().take(17).collect::<String>())
                 } else {
                     display_name
                 };
+                if dir.has_lossy_name() {
+                    label_text.push_str(" �");
+                }
                 
                 let label_response = ui.label(&label_text);
                 if label_response.hovered() {
@@ -157,7 +162,7 @@ fn render_directory(
                     ui.painter().rect_filled(
                         rect,
                         0.0,
-                        theme::row_hover_bg(),
+                        theme.row_hover_bg,
                     );
                     ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
                 }
@@ -166,9 +171,10 @@ fn render_directory(
         }
     );
 
+    let mut clicked = None;
     if let Some(label_response) = label_response_opt {
         if label_response.clicked() {
-            on_path_click(dir.path.clone());
+            clicked = Some(dir.path.clone());
         }
 
         if is_current {
@@ -176,7 +182,7 @@ fn render_directory(
             ui.painter().rect_stroke(
                 rect,
                 2.0,
-                egui::Stroke::new(2.0, theme::TREE_CURRENT_STROKE),
+                egui::Stroke::new(2.0, theme.tree_current_stroke),
             );
         }
     }
@@ -189,40 +195,33 @@ fn render_directory(
         
         for (idx, child_dir) in child_dirs.iter().enumerate() {
             let is_child_last = idx == child_dirs.len() - 1;
-            render_directory(
-                ui,
-                tag_db,
-                child_dir,
-                current_path,
-                expanded,
-                show_hidden_files,
-                on_path_click,
-                depth + 1,
-                max_width,
-                is_child_last,
-                new_prefix.clone(),
-                tree_root,
-            );
+            let child_clicked =
+                render_directory(ui, tree, child_dir, expanded, depth + 1, is_child_last, new_prefix.clone());
+            clicked = clicked.or(child_clicked);
         }
     }
+    clicked
 }
 
-fn has_child_directories(tag_db: &TagDatabase, dir_path: &PathBuf, show_hidden_files: bool) -> bool {
-    if let Ok(files) = tag_db.get_files_in_directory(dir_path) {
+fn has_child_directories(tree: &TreeContext, dir_path: &Path) -> bool {
+    if let Ok(files) = tree.tag_db.get_files_in_directory(dir_path) {
         files.iter().any(|f| {
-            matches!(f.file_type, FileType::Directory) && (show_hidden_files || !f.name.starts_with('.'))
+            f.is_dir()
+                && (tree.show_hidden_files || !tree.hidden.is_hidden(f))
+                && (tree.show_ignored_files || !f.ignored)
         })
     } else {
         false
     }
 }
 
-fn get_child_directories(tag_db: &TagDatabase, dir_path: &PathBuf, show_hidden_files: bool) -> Vec<FileEntry> {
-    if let Ok(files) = tag_db.get_files_in_directory(dir_path) {
+fn get_child_directories(tree: &TreeContext, dir_path: &Path) -> Vec<FileEntry> {
+    if let Ok(files) = tree.tag_db.get_files_in_directory(dir_path) {
         let mut dirs: Vec<FileEntry> = files
             .into_iter()
-            .filter(|f| matches!(f.file_type, FileType::Directory))
-            .filter(|f| show_hidden_files || !f.name.starts_with('.'))
+            .filter(|f| f.is_dir())
+            .filter(|f| tree.show_hidden_files || !tree.hidden.is_hidden(f))
+            .filter(|f| tree.show_ignored_files || !f.ignored)
             .collect();
         dirs.sort_by(|a, b| a.name.cmp(&b.name));
         dirs
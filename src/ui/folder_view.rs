@@ -1,23 +1,33 @@
 use eframe::egui;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+use crate::archive;
 use crate::file_associations::FileAssociations;
 use crate::tag_db::FileEntry;
-use crate::ui::file_list::render_file_list;
+use crate::ui::file_list::{render_file_list, FileListAction, ListScroll, SortKey, SortOrder};
 
+#[allow(clippy::too_many_arguments)]
 pub fn render_folder_view(
     files: Vec<FileEntry>,
     current_path: PathBuf,
     on_path_change: &mut dyn FnMut(PathBuf),
     selected_file_index: Option<usize>,
+    marked: &BTreeMap<PathBuf, FileEntry>,
+    scroll: &mut ListScroll,
+    sort: &mut SortOrder,
     file_associations: &FileAssociations,
     ui: &mut egui::Ui,
-) {
+) -> Option<FileListAction> {
     ui.vertical(|ui| {
         ui.horizontal(|ui| {
             let components: Vec<_> = current_path.components().collect();
+            // Inside an archive the path continues as `archive.zip › inner/dir`.
+            let archive_depth = archive::split(&current_path).map(|(archive, _)| archive.components().count());
             for (i, component) in components.iter().enumerate() {
-                if i > 0 {
+                if Some(i) == archive_depth {
+                    ui.label(" › ");
+                } else if i > 0 {
                     ui.label(" / ");
                 }
                 let path = components[..=i]
@@ -29,11 +39,29 @@ pub fn render_folder_view(
                     on_path_change(path.clone());
                 }
             }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if sort.key != SortKey::Default {
+                    let arrow = if sort.descending { "⬇" } else { "⬆" };
+                    if ui.small_button(arrow).on_hover_text("Reverse order").clicked() {
+                        sort.descending = !sort.descending;
+                    }
+                }
+                egui::ComboBox::from_id_source("folder_sort")
+                    .selected_text(format!("Sort: {}", sort.key.label()))
+                    .show_ui(ui, |ui| {
+                        for key in SortKey::ALL {
+                            ui.selectable_value(&mut sort.key, key, key.label());
+                        }
+                    });
+            });
         });
         ui.separator();
 
         ui.allocate_ui(ui.available_size(), |ui| {
-            render_file_list(ui, files, Some(on_path_change), selected_file_index, file_associations);
-        });
-    });
+            render_file_list(ui, files, Some(on_path_change), selected_file_index, marked, scroll, file_associations)
+        })
+        .inner
+    })
+    .inner
 }
@@ -0,0 +1,198 @@
+use eframe::egui;
+use std::time::Duration;
+
+use crate::indexer::{IndexStats, ProgressSnapshot};
+
+pub enum IndexingAction {
+    ShowPanel,
+    Cancel,
+    Reindex,
+    Close,
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
+
+fn summarize_stats(stats: &IndexStats) -> String {
+    format!(
+        "{} — indexed {} entries ({} changed, {} removed) in {} ({:.0} files/s)",
+        if stats.cancelled { "Cancelled" } else { "Ready" },
+        stats.entries,
+        stats.written,
+        stats.removed,
+        format_duration(stats.elapsed),
+        stats.files_per_sec()
+    )
+}
+
+/// The indexing part of the status bar; clicking it opens the details panel.
+pub fn render_indexing_status(
+    ui: &mut egui::Ui,
+    is_indexing: bool,
+    progress: &ProgressSnapshot,
+    last_stats: Option<&IndexStats>,
+) -> Option<IndexingAction> {
+    let mut action = None;
+
+    let text = if is_indexing {
+        let mut text = format!(
+            "Indexing… {} folders, {} entries",
+            progress.dirs_done, progress.entries
+        );
+        if progress.errors > 0 {
+            text.push_str(&format!(", {} errors", progress.errors));
+        }
+        if let Some(name) = progress.current_path.file_name() {
+            text.push_str(&format!(" — {}", name.to_string_lossy()));
+        }
+        text
+    } else if let Some(stats) = last_stats {
+        summarize_stats(stats)
+    } else {
+        "Ready".to_string()
+    };
+
+    let label = ui
+        .add(egui::Label::new(text).sense(egui::Sense::click()))
+        .on_hover_text("Show indexing details");
+    if label.clicked() {
+        action = Some(IndexingAction::ShowPanel);
+    }
+
+    if is_indexing {
+        if progress.cancelled {
+            ui.label(egui::RichText::new("Cancelling…").weak());
+        } else if ui.small_button("✖ Cancel").on_hover_text("Stop indexing").clicked() {
+            action = Some(IndexingAction::Cancel);
+        }
+    }
+
+    action
+}
+
+pub fn render_indexing_panel(
+    ctx: &egui::Context,
+    is_indexing: bool,
+    progress: &ProgressSnapshot,
+    last_stats: Option<&IndexStats>,
+) -> Option<IndexingAction> {
+    let mut action = None;
+    let mut open = true;
+
+    egui::Window::new("Indexing")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_width(420.0)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            if is_indexing {
+                egui::Grid::new("indexing_progress")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Roots:");
+                        ui.label(format!("{} of {}", progress.roots_done, progress.roots_total));
+                        ui.end_row();
+
+                        ui.label("Folders:");
+                        ui.label(format!("{} of {} found", progress.dirs_done, progress.dirs_found));
+                        ui.end_row();
+
+                        ui.label("Entries:");
+                        ui.label(progress.entries.to_string());
+                        ui.end_row();
+
+                        ui.label("Errors:");
+                        ui.label(progress.errors.to_string());
+                        ui.end_row();
+
+                        ui.label("Elapsed:");
+                        ui.label(format_duration(progress.elapsed));
+                        ui.end_row();
+
+                        ui.label("Remaining:");
+                        ui.label(match progress.estimate_remaining() {
+                            Some(estimate) => format!("about {}", format_duration(estimate)),
+                            None => "estimating…".to_string(),
+                        })
+                        .on_hover_text("Based on the folders found so far");
+                        ui.end_row();
+
+                        ui.label("Current:");
+                        ui.label(progress.current_path.to_string_lossy());
+                        ui.end_row();
+                    });
+
+                if progress.dirs_found > 0 {
+                    ui.add(egui::ProgressBar::new(
+                        progress.dirs_done as f32 / progress.dirs_found as f32,
+                    ));
+                }
+            } else if let Some(stats) = last_stats {
+                egui::Grid::new("indexing_last_run")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Last run:");
+                        ui.label(if stats.cancelled { "cancelled" } else { "completed" });
+                        ui.end_row();
+
+                        ui.label("Entries:");
+                        ui.label(stats.entries.to_string());
+                        ui.end_row();
+
+                        ui.label("Changed:");
+                        ui.label(stats.written.to_string());
+                        ui.end_row();
+
+                        ui.label("Removed:");
+                        ui.label(stats.removed.to_string());
+                        ui.end_row();
+
+                        ui.label("Errors:");
+                        ui.label(stats.errors.to_string());
+                        ui.end_row();
+
+                        ui.label("Duration:");
+                        ui.label(format!(
+                            "{} ({:.0} files/s)",
+                            format_duration(stats.elapsed),
+                            stats.files_per_sec()
+                        ));
+                        ui.end_row();
+                    });
+            } else {
+                ui.label("No indexing run has finished yet.");
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if is_indexing {
+                    if ui
+                        .add_enabled(!progress.cancelled, egui::Button::new("Cancel indexing"))
+                        .clicked()
+                    {
+                        action = Some(IndexingAction::Cancel);
+                    }
+                } else if ui.button("Re-index now").clicked() {
+                    action = Some(IndexingAction::Reindex);
+                }
+                if ui.button("Close").clicked() {
+                    action = Some(IndexingAction::Close);
+                }
+            });
+        });
+
+    if !open {
+        action = Some(IndexingAction::Close);
+    }
+
+    action
+}
@@ -0,0 +1,101 @@
+use eframe::egui;
+
+use crate::logging::{self, LogLine};
+use crate::ui::theme;
+
+/// Viewer filters; the records themselves are read from `logging` each frame.
+pub struct LogViewer {
+    min_level: log::Level,
+    filter: String,
+}
+
+impl Default for LogViewer {
+    fn default() -> Self {
+        LogViewer {
+            min_level: log::Level::Info,
+            filter: String::new(),
+        }
+    }
+}
+
+fn format_line(line: &LogLine) -> String {
+    format!(
+        "{} {:<5} {}: {}",
+        line.time.format("%H:%M:%S%.3f"),
+        line.level,
+        line.target,
+        line.message
+    )
+}
+
+fn level_color(ui: &egui::Ui, level: log::Level) -> egui::Color32 {
+    match level {
+        log::Level::Error => ui.visuals().error_fg_color,
+        log::Level::Warn => ui.visuals().warn_fg_color,
+        log::Level::Info => ui.visuals().text_color(),
+        log::Level::Debug | log::Level::Trace => theme::current(ui.ctx()).text_secondary,
+    }
+}
+
+/// Recent log records with level and text filters. Returns false once the
+/// window is closed.
+pub fn render_log_viewer(ctx: &egui::Context, viewer: &mut LogViewer) -> bool {
+    let mut open = true;
+    let lines: Vec<LogLine> = logging::recent()
+        .into_iter()
+        .filter(|line| line.level <= viewer.min_level)
+        .filter(|line| {
+            viewer.filter.is_empty()
+                || line.message.to_lowercase().contains(&viewer.filter.to_lowercase())
+                || line.target.contains(&viewer.filter)
+        })
+        .collect();
+
+    egui::Window::new("Log")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_size(egui::vec2(640.0, 360.0))
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("log_level")
+                    .selected_text(viewer.min_level.as_str())
+                    .show_ui(ui, |ui| {
+                        for level in log::Level::iter() {
+                            ui.selectable_value(&mut viewer.min_level, level, level.as_str());
+                        }
+                    });
+                ui.add(
+                    egui::TextEdit::singleline(&mut viewer.filter)
+                        .hint_text("Filter by message or module")
+                        .desired_width(220.0),
+                );
+                if ui.button("Copy").on_hover_text("Copy the shown lines").clicked() {
+                    let text: Vec<String> = lines.iter().map(format_line).collect();
+                    ui.output_mut(|o| o.copied_text = text.join("\n"));
+                }
+            });
+            match logging::log_file_path() {
+                Some(path) => ui.label(
+                    egui::RichText::new(format!("Full log: {}", path.display())).weak(),
+                ),
+                None => ui.label(egui::RichText::new("The log file could not be opened").weak()),
+            };
+            ui.separator();
+
+            egui::ScrollArea::both()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in &lines {
+                        ui.label(
+                            egui::RichText::new(format_line(line))
+                                .monospace()
+                                .color(level_color(ui, line.level)),
+                        );
+                    }
+                });
+        });
+
+    open
+}
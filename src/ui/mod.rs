@@ -3,3 +3,16 @@ pub mod tag_view;
 pub mod file_list;
 pub mod file_tree;
 pub mod theme;
+pub mod tab_bar;
+pub mod navigation;
+pub mod favorites;
+pub mod recent_view;
+pub mod smart_folders;
+pub mod smart_view;
+pub mod settings;
+pub mod indexing;
+pub mod properties;
+pub mod command_palette;
+pub mod notifications;
+pub mod log_viewer;
+pub mod batch_rename;
@@ -0,0 +1,51 @@
+use eframe::egui;
+
+use crate::history::History;
+
+const MENU_ENTRIES: usize = 15;
+
+/// Returns the number of steps to move through the history, negative for back.
+pub fn render_navigation_buttons(ui: &mut egui::Ui, history: &History) -> Option<isize> {
+    let mut offset = None;
+
+    if ui
+        .add_enabled(history.can_go_back(), egui::Button::new("◀"))
+        .on_hover_text("Back (Alt+←)")
+        .clicked()
+    {
+        offset = Some(-1);
+    }
+
+    if ui
+        .add_enabled(history.can_go_forward(), egui::Button::new("▶"))
+        .on_hover_text("Forward (Alt+→)")
+        .clicked()
+    {
+        offset = Some(1);
+    }
+
+    ui.menu_button("⏷", |ui| {
+        let forward: Vec<_> = history.forward_entries().take(MENU_ENTRIES).collect();
+        for (i, location) in forward.iter().enumerate().rev() {
+            if ui.button(location.label()).clicked() {
+                offset = Some(i as isize + 1);
+                ui.close_menu();
+            }
+        }
+
+        if let Some(current) = history.current() {
+            ui.label(egui::RichText::new(current.label()).strong());
+        }
+
+        for (i, location) in history.back_entries().take(MENU_ENTRIES).enumerate() {
+            if ui.button(location.label()).clicked() {
+                offset = Some(-(i as isize) - 1);
+                ui.close_menu();
+            }
+        }
+    })
+    .response
+    .on_hover_text("Recent locations");
+
+    offset
+}
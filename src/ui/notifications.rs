@@ -0,0 +1,130 @@
+use eframe::egui;
+
+use crate::notifications::{Notification, NotificationCenter, Severity};
+use crate::ui::theme;
+
+pub enum NotificationAction {
+    ShowPanel,
+    DismissToast(usize),
+    Clear,
+    ShowLog,
+    Close,
+}
+
+fn severity_color(ui: &egui::Ui, severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Error => ui.visuals().error_fg_color,
+        Severity::Warning => ui.visuals().warn_fg_color,
+        Severity::Info => ui.visuals().text_color(),
+    }
+}
+
+fn path_label(ui: &mut egui::Ui, notification: &Notification) {
+    if let Some(path) = &notification.path {
+        ui.label(
+            egui::RichText::new(path.to_string_lossy())
+                .size(11.0)
+                .color(theme::current(ui.ctx()).text_secondary),
+        );
+    }
+}
+
+/// The bell in the status bar, with the number of unread notifications.
+pub fn render_notification_status(ui: &mut egui::Ui, center: &NotificationCenter) -> Option<NotificationAction> {
+    let text = match center.unread() {
+        0 => "🔔".to_string(),
+        unread => format!("🔔 {}", unread),
+    };
+    ui.small_button(text)
+        .on_hover_text("Show notifications")
+        .clicked()
+        .then_some(NotificationAction::ShowPanel)
+}
+
+/// Recent errors stacked in the bottom-right corner until they expire or
+/// are dismissed; clicking one opens the notifications panel.
+pub fn render_toasts(ctx: &egui::Context, center: &NotificationCenter) -> Option<NotificationAction> {
+    if !center.has_toasts() {
+        return None;
+    }
+    ctx.request_repaint_after(std::time::Duration::from_secs(1));
+
+    let mut action = None;
+    egui::Area::new(egui::Id::new("notification_toasts"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -36.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            ui.set_max_width(340.0);
+            for (index, notification) in center.toasts().enumerate() {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let color = severity_color(ui, notification.severity);
+                        ui.label(egui::RichText::new(notification.severity.icon()).color(color));
+                        let message = ui.add(
+                            egui::Label::new(&notification.message)
+                                .wrap(true)
+                                .sense(egui::Sense::click()),
+                        );
+                        if message.clicked() {
+                            action = Some(NotificationAction::ShowPanel);
+                        }
+                        if ui.small_button("×").on_hover_text("Dismiss").clicked() {
+                            action = Some(NotificationAction::DismissToast(index));
+                        }
+                    });
+                    path_label(ui, notification);
+                });
+                ui.add_space(4.0);
+            }
+        });
+    action
+}
+
+/// Every notification so far, newest first, with its time and path.
+pub fn render_notifications_panel(ctx: &egui::Context, center: &NotificationCenter) -> Option<NotificationAction> {
+    let mut action = None;
+    let mut open = true;
+
+    egui::Window::new("Notifications")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_width(480.0)
+        .show(ctx, |ui| {
+            let mut any = false;
+            egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                for notification in center.history() {
+                    any = true;
+                    ui.horizontal(|ui| {
+                        let color = severity_color(ui, notification.severity);
+                        ui.label(egui::RichText::new(notification.severity.icon()).color(color))
+                            .on_hover_text(notification.severity.label());
+                        ui.monospace(notification.time.format("%H:%M:%S").to_string());
+                        ui.add(egui::Label::new(&notification.message).wrap(true));
+                    });
+                    path_label(ui, notification);
+                    ui.separator();
+                }
+            });
+            if !any {
+                ui.label(egui::RichText::new("No notifications").weak());
+            }
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(any, egui::Button::new("Clear")).clicked() {
+                    action = Some(NotificationAction::Clear);
+                }
+                if ui.button("Show log").clicked() {
+                    action = Some(NotificationAction::ShowLog);
+                }
+                if ui.button("Close").clicked() {
+                    action = Some(NotificationAction::Close);
+                }
+            });
+        });
+
+    if !open {
+        action = Some(NotificationAction::Close);
+    }
+    action
+}
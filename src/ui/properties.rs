@@ -0,0 +1,236 @@
+use chrono::{Local, TimeZone};
+use eframe::egui;
+use std::sync::atomic::Ordering;
+
+use crate::properties::{ChecksumKind, ChecksumState, Properties};
+use crate::tag_db::FileType;
+use crate::ui::file_list::format_size;
+
+fn format_time(secs: i64) -> String {
+    match Local.timestamp_opt(secs, 0).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => "—".to_string(),
+    }
+}
+
+fn describe_id(name: Option<&str>, id: u32) -> String {
+    match name {
+        Some(name) => format!("{} ({})", name, id),
+        None => id.to_string(),
+    }
+}
+
+/// Shows everything known about a file or folder, with folder totals that
+/// fill in as they are counted and checksums computed on request. Returns
+/// false once closed.
+pub fn render_properties_window(ctx: &egui::Context, properties: &mut Properties) -> bool {
+    let mut open = true;
+    let mut requested = None;
+    let file = &properties.file;
+    let metadata = &file.metadata;
+
+    egui::Window::new(format!("{} — Properties", file.name))
+        .id(egui::Id::new("properties_window"))
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_width(420.0)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            egui::Grid::new("properties_grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Path:");
+                    ui.label(file.path.to_string_lossy());
+                    ui.end_row();
+
+                    if let Some(name) = file.path.file_name().filter(|_| file.has_lossy_name()) {
+                        ui.label("Raw name:");
+                        ui.label(format!("{:?}", name))
+                            .on_hover_text("The name is not valid UTF-8; invalid bytes are shown escaped");
+                        ui.end_row();
+                    }
+
+                    ui.label("Kind:");
+                    ui.label(match file.file_type {
+                        FileType::File => "File",
+                        FileType::Directory => "Folder",
+                        FileType::Symlink if file.is_broken_link() => "Symbolic link (broken)",
+                        FileType::Symlink => "Symbolic link",
+                    });
+                    ui.end_row();
+
+                    if let Some(link) = &file.link {
+                        ui.label("Link target:");
+                        ui.label(link.target.to_string_lossy());
+                        ui.end_row();
+                    }
+
+                    if let Some(kind) = metadata.kind {
+                        ui.label("Type:");
+                        ui.label(match &metadata.mime {
+                            Some(mime) => format!("{} {} ({})", kind.icon(), kind.label(), mime),
+                            None => format!("{} {}", kind.icon(), kind.label()),
+                        });
+                        ui.end_row();
+                    }
+
+                    if let Some(extension) = &metadata.extension {
+                        ui.label("Extension:");
+                        ui.label(extension);
+                        ui.end_row();
+                    }
+
+                    if let Some(summary) = &properties.folder {
+                        let done = summary.done.load(Ordering::Relaxed);
+                        let files = summary.files.load(Ordering::Relaxed);
+                        let folders = summary.folders.load(Ordering::Relaxed);
+                        let size = summary.size.load(Ordering::Relaxed);
+                        let allocated = summary.allocated.load(Ordering::Relaxed);
+                        let errors = summary.errors.load(Ordering::Relaxed);
+
+                        ui.label("Contains:");
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} files, {} folders", files, folders));
+                            if !done {
+                                ui.spinner();
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Total size:");
+                        ui.label(format!("{} ({} bytes)", format_size(size), size));
+                        ui.end_row();
+
+                        if allocated > 0 {
+                            ui.label("On disk:");
+                            ui.label(format_size(allocated));
+                            ui.end_row();
+                        }
+
+                        if errors > 0 {
+                            ui.label("Unreadable:");
+                            ui.colored_label(ui.visuals().warn_fg_color, format!("{} items not counted", errors));
+                            ui.end_row();
+                        }
+                    } else {
+                        ui.label("Size:");
+                        ui.label(format!("{} ({} bytes)", format_size(file.size), file.size));
+                        ui.end_row();
+
+                        if let Some(allocated) = properties.allocated {
+                            ui.label("On disk:");
+                            ui.label(format!("{} ({} bytes)", format_size(allocated), allocated));
+                            ui.end_row();
+                        }
+                    }
+
+                    ui.label("Modified:");
+                    ui.label(format_time(file.modified));
+                    ui.end_row();
+
+                    ui.label("Created:");
+                    ui.label(metadata.created.map(format_time).unwrap_or_else(|| "—".to_string()));
+                    ui.end_row();
+
+                    ui.label("Accessed:");
+                    ui.label(format_time(metadata.accessed));
+                    ui.end_row();
+
+                    ui.label("Permissions:");
+                    ui.label(format!(
+                        "{} ({:04o})",
+                        metadata.permissions_string(),
+                        metadata.mode & 0o7777
+                    ));
+                    ui.end_row();
+
+                    ui.label("Owner:");
+                    ui.label(describe_id(metadata.owner.as_deref(), metadata.uid));
+                    ui.end_row();
+
+                    ui.label("Group:");
+                    ui.label(describe_id(metadata.group.as_deref(), metadata.gid));
+                    ui.end_row();
+
+                    ui.label("Device / inode:");
+                    ui.label(format!("{} / {}", metadata.device, metadata.inode));
+                    ui.end_row();
+
+                    ui.label("Hard links:");
+                    ui.label(metadata.nlink.to_string());
+                    ui.end_row();
+
+                    ui.label("Tags:");
+                    if properties.tags.is_empty() {
+                        ui.label(egui::RichText::new("none").weak());
+                    } else {
+                        ui.label(properties.tags.join(", "));
+                    }
+                    ui.end_row();
+                });
+
+            if !properties.xattrs.is_empty() {
+                ui.collapsing(format!("Extended attributes ({})", properties.xattrs.len()), |ui| {
+                    egui::Grid::new("properties_xattrs")
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (name, value) in &properties.xattrs {
+                                ui.label(egui::RichText::new(name).monospace());
+                                ui.label(value);
+                                ui.end_row();
+                            }
+                        });
+                });
+            }
+
+            if properties.can_checksum {
+                ui.separator();
+                egui::Grid::new("properties_checksums")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for kind in ChecksumKind::ALL {
+                            let state = properties.checksums.get(&kind).map(|state| state.lock().unwrap().clone());
+                            match state {
+                                None => {
+                                    if ui.button(kind.label()).clicked() {
+                                        requested = Some(kind);
+                                    }
+                                    ui.label("");
+                                }
+                                Some(ChecksumState::Running) => {
+                                    ui.label(format!("{}:", kind.label()));
+                                    ui.spinner();
+                                }
+                                Some(ChecksumState::Done(digest)) => {
+                                    ui.label(format!("{}:", kind.label()));
+                                    let label = egui::Label::new(egui::RichText::new(&digest).monospace());
+                                    if ui
+                                        .add(label.sense(egui::Sense::click()))
+                                        .on_hover_text("Click to copy")
+                                        .clicked()
+                                    {
+                                        ui.output_mut(|output| output.copied_text = digest);
+                                    }
+                                }
+                                Some(ChecksumState::Failed(error)) => {
+                                    ui.label(format!("{}:", kind.label()));
+                                    ui.colored_label(ui.visuals().error_fg_color, error);
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            }
+        });
+
+    if let Some(kind) = requested {
+        properties.compute_checksum(kind);
+    }
+    if properties.is_busy() {
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+    }
+    open
+}
@@ -0,0 +1,28 @@
+use eframe::egui;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::file_associations::FileAssociations;
+use crate::tag_db::FileEntry;
+use crate::ui::file_list::{render_file_list, FileListAction, ListScroll};
+
+pub fn render_recent_view(
+    files: Vec<FileEntry>,
+    on_dir_click: &mut dyn FnMut(PathBuf),
+    selected_file_index: Option<usize>,
+    marked: &BTreeMap<PathBuf, FileEntry>,
+    scroll: &mut ListScroll,
+    file_associations: &FileAssociations,
+    ui: &mut egui::Ui,
+) -> Option<FileListAction> {
+    ui.vertical(|ui| {
+        ui.heading("Recently used");
+        ui.separator();
+
+        ui.allocate_ui(ui.available_size(), |ui| {
+            render_file_list(ui, files, Some(on_dir_click), selected_file_index, marked, scroll, file_associations)
+        })
+        .inner
+    })
+    .inner
+}
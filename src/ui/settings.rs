@@ -0,0 +1,307 @@
+use eframe::egui;
+use std::path::PathBuf;
+
+use crate::keymap::{Action, Keymap, KeymapPreset};
+use crate::os_path;
+use crate::settings::{self, AppearanceSettings, HiddenSettings, IndexRoot, IndexSettings, Settings};
+use crate::ui::theme::{self, Themes};
+
+pub enum SettingsAction {
+    Save { reindex: bool },
+    Cancel,
+}
+
+/// Text-field friendly copy of one index root.
+struct RootDraft {
+    path: String,
+    original_path: Option<PathBuf>,
+    unlimited_depth: bool,
+    max_depth: usize,
+    follow_symlinks: bool,
+    same_filesystem: bool,
+    respect_ignore_files: bool,
+    index_archives: bool,
+    exclude: String,
+}
+
+pub struct SettingsDraft {
+    roots: Vec<RootDraft>,
+    hide_patterns: String,
+    hide_backups: bool,
+    honor_hidden_files: bool,
+    theme: String,
+    pub keymap_preset: KeymapPreset,
+}
+
+impl SettingsDraft {
+    pub fn new(settings: &Settings, keymap_preset: KeymapPreset) -> Self {
+        SettingsDraft {
+            roots: settings
+                .index
+                .roots
+                .iter()
+                .map(|root| RootDraft {
+                    path: root.path.to_string_lossy().to_string(),
+                    original_path: Some(root.path.clone()),
+                    unlimited_depth: root.max_depth.is_none(),
+                    max_depth: root.max_depth.unwrap_or(3),
+                    follow_symlinks: root.follow_symlinks,
+                    same_filesystem: root.same_filesystem,
+                    respect_ignore_files: root.respect_ignore_files,
+                    index_archives: root.index_archives,
+                    exclude: root.exclude.join(", "),
+                })
+                .collect(),
+            hide_patterns: settings.hidden.patterns.join(", "),
+            hide_backups: settings.hidden.hide_backups,
+            honor_hidden_files: settings.hidden.honor_hidden_files,
+            theme: settings.appearance.theme.clone(),
+            keymap_preset,
+        }
+    }
+
+    pub fn to_hidden_settings(&self) -> HiddenSettings {
+        HiddenSettings {
+            patterns: split_patterns(&self.hide_patterns),
+            hide_backups: self.hide_backups,
+            honor_hidden_files: self.honor_hidden_files,
+        }
+    }
+
+    pub fn to_appearance_settings(&self) -> AppearanceSettings {
+        AppearanceSettings {
+            theme: self.theme.clone(),
+        }
+    }
+
+    pub fn to_index_settings(&self) -> IndexSettings {
+        IndexSettings {
+            roots: self
+                .roots
+                .iter()
+                .filter(|root| !root.path.trim().is_empty())
+                .map(|root| IndexRoot {
+                    path: settings::expand_home(&os_path::from_edited(root.path.trim(), root.original_path.as_deref())),
+                    max_depth: (!root.unlimited_depth).then_some(root.max_depth),
+                    follow_symlinks: root.follow_symlinks,
+                    same_filesystem: root.same_filesystem,
+                    respect_ignore_files: root.respect_ignore_files,
+                    exclude: split_patterns(&root.exclude),
+                    index_archives: root.index_archives,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn split_patterns(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn render_settings_window(
+    ctx: &egui::Context,
+    draft: &mut SettingsDraft,
+    is_indexing: bool,
+    keymap: &Keymap,
+    themes: &Themes,
+) -> Option<SettingsAction> {
+    let mut action = None;
+    let link_broken = theme::current(ctx).link_broken;
+
+    egui::Window::new("Settings")
+        .collapsible(false)
+        .resizable(true)
+        .default_width(460.0)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.heading("Index roots");
+            ui.label(egui::RichText::new("Exclude patterns are globs matched against names and full paths.").weak());
+            ui.separator();
+
+            let mut remove_index = None;
+            egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                for (index, root) in draft.roots.iter_mut().enumerate() {
+                    egui::Grid::new(("index_root", index))
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Path:");
+                            ui.horizontal(|ui| {
+                                ui.add(egui::TextEdit::singleline(&mut root.path).desired_width(300.0));
+                                if ui.button("🗑").on_hover_text("Remove root").clicked() {
+                                    remove_index = Some(index);
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label("Depth:");
+                            ui.horizontal(|ui| {
+                                ui.add_enabled(
+                                    !root.unlimited_depth,
+                                    egui::DragValue::new(&mut root.max_depth).clamp_range(1..=64),
+                                );
+                                ui.checkbox(&mut root.unlimited_depth, "Unlimited");
+                            });
+                            ui.end_row();
+
+                            ui.label("");
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut root.follow_symlinks, "Follow symlinks");
+                                ui.checkbox(&mut root.same_filesystem, "Stay on same filesystem");
+                            });
+                            ui.end_row();
+
+                            ui.label("");
+                            ui.checkbox(
+                                &mut root.respect_ignore_files,
+                                "Respect .gitignore, .ignore and .fmsignore",
+                            );
+                            ui.end_row();
+
+                            ui.label("");
+                            ui.checkbox(&mut root.index_archives, "Index zip and tar archive contents");
+                            ui.end_row();
+
+                            ui.label("Exclude:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut root.exclude)
+                                    .hint_text("node_modules, target, .cache")
+                                    .desired_width(300.0),
+                            );
+                            ui.end_row();
+                        });
+                    ui.separator();
+                }
+            });
+
+            if let Some(index) = remove_index {
+                draft.roots.remove(index);
+            }
+
+            if ui.button("Add root").clicked() {
+                draft.roots.push(RootDraft {
+                    path: String::new(),
+                    original_path: None,
+                    unlimited_depth: false,
+                    max_depth: 3,
+                    follow_symlinks: false,
+                    same_filesystem: true,
+                    respect_ignore_files: true,
+                    index_archives: false,
+                    exclude: String::new(),
+                });
+            }
+
+            ui.add_space(8.0);
+            ui.heading("Hidden files");
+            ui.label(
+                egui::RichText::new("Dot files are always hidden; Cmd+. shows everything.").weak(),
+            );
+            egui::Grid::new("hidden_settings")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("");
+                    ui.checkbox(&mut draft.hide_backups, "Hide backup files (*~)");
+                    ui.end_row();
+
+                    ui.label("");
+                    ui.checkbox(&mut draft.honor_hidden_files, "Hide names listed in .hidden files");
+                    ui.end_row();
+
+                    ui.label("Hide:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.hide_patterns)
+                            .hint_text("*.pyc, __pycache__, Thumbs.db")
+                            .desired_width(300.0),
+                    );
+                    ui.end_row();
+                });
+
+            ui.add_space(8.0);
+            ui.heading("Appearance");
+            ui.horizontal(|ui| {
+                ui.label("Theme:");
+                let selected = if draft.theme == theme::FOLLOW_SYSTEM {
+                    "Follow system"
+                } else {
+                    draft.theme.as_str()
+                };
+                egui::ComboBox::from_id_source("theme")
+                    .selected_text(selected.to_string())
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut draft.theme, theme::FOLLOW_SYSTEM.to_string(), "Follow system");
+                        for name in themes.names() {
+                            ui.selectable_value(&mut draft.theme, name.to_string(), name);
+                        }
+                    });
+            });
+            ui.label(
+                egui::RichText::new("Add themes as .json or .toml files in ~/.fms/themes.").weak(),
+            );
+            for problem in themes.problems() {
+                ui.colored_label(link_broken, format!("⚠ {}", problem));
+            }
+
+            ui.add_space(8.0);
+            ui.heading("Keyboard");
+            ui.horizontal(|ui| {
+                ui.label("Preset:");
+                egui::ComboBox::from_id_source("keymap_preset")
+                    .selected_text(draft.keymap_preset.label())
+                    .show_ui(ui, |ui| {
+                        for preset in KeymapPreset::ALL {
+                            ui.selectable_value(&mut draft.keymap_preset, preset, preset.label());
+                        }
+                    });
+            });
+            ui.label(
+                egui::RichText::new("Override bindings in ~/.fms/keymap.json, e.g. \"Cmd+Shift+N\": \"new_folder\".")
+                    .weak(),
+            );
+            for problem in keymap.problems() {
+                ui.colored_label(link_broken, format!("⚠ {}", problem));
+            }
+            egui::CollapsingHeader::new("Current bindings")
+                .id_source("keymap_bindings")
+                .show(ui, |ui| {
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        egui::Grid::new("keymap_bindings_grid")
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for action in Action::all() {
+                                    ui.label(action.label());
+                                    ui.label(egui::RichText::new(action.context().label()).weak());
+                                    ui.monospace(keymap.bindings_for(action).join(", "));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    action = Some(SettingsAction::Save { reindex: false });
+                }
+                if ui
+                    .add_enabled(!is_indexing, egui::Button::new("Save and re-index"))
+                    .clicked()
+                {
+                    action = Some(SettingsAction::Save { reindex: true });
+                }
+                if ui.button("Cancel").clicked() {
+                    action = Some(SettingsAction::Cancel);
+                }
+            });
+        });
+
+    if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+        action = Some(SettingsAction::Cancel);
+    }
+
+    action
+}
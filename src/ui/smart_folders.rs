@@ -0,0 +1,202 @@
+use eframe::egui;
+use std::path::PathBuf;
+
+use crate::os_path;
+use crate::smart_folders::{SmartFolder, SmartFolders, SmartScope};
+
+pub enum SmartFolderAction {
+    Open(String),
+    Edit(String),
+    Export(String),
+    Delete(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ScopeKind {
+    Everywhere,
+    Directory,
+    Tag,
+}
+
+/// Editable copy of a smart folder shown in the editor window.
+pub struct SmartFolderDraft {
+    pub previous_name: Option<String>,
+    pub name: String,
+    pub query: String,
+    pub scope_kind: ScopeKind,
+    pub directory: String,
+    /// The scope path being edited, which `directory` may show lossily.
+    original_directory: Option<PathBuf>,
+    pub tag: String,
+}
+
+impl SmartFolderDraft {
+    pub fn new(query: &str, scope: SmartScope) -> Self {
+        let mut draft = Self::from_folder(&SmartFolder {
+            name: query.to_string(),
+            query: query.to_string(),
+            scope,
+        });
+        draft.previous_name = None;
+        draft
+    }
+
+    pub fn from_folder(folder: &SmartFolder) -> Self {
+        let (scope_kind, directory, tag) = match &folder.scope {
+            SmartScope::Everywhere => (ScopeKind::Everywhere, String::new(), String::new()),
+            SmartScope::Directory { path } => {
+                (ScopeKind::Directory, path.to_string_lossy().to_string(), String::new())
+            }
+            SmartScope::Tag { tag } => (ScopeKind::Tag, String::new(), tag.clone()),
+        };
+
+        SmartFolderDraft {
+            previous_name: Some(folder.name.clone()),
+            name: folder.name.clone(),
+            query: folder.query.clone(),
+            scope_kind,
+            directory,
+            original_directory: match &folder.scope {
+                SmartScope::Directory { path } => Some(path.clone()),
+                _ => None,
+            },
+            tag,
+        }
+    }
+
+    pub fn to_folder(&self) -> Option<SmartFolder> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let scope = match self.scope_kind {
+            ScopeKind::Everywhere => SmartScope::Everywhere,
+            ScopeKind::Directory if !self.directory.trim().is_empty() => SmartScope::Directory {
+                path: os_path::from_edited(self.directory.trim(), self.original_directory.as_deref()),
+            },
+            ScopeKind::Tag if !self.tag.trim().is_empty() => SmartScope::Tag {
+                tag: self.tag.trim().to_string(),
+            },
+            _ => return None,
+        };
+
+        Some(SmartFolder {
+            name: name.to_string(),
+            query: self.query.clone(),
+            scope,
+        })
+    }
+}
+
+pub enum EditorResult {
+    Save(SmartFolder),
+    Cancel,
+}
+
+pub fn render_smart_folders(
+    ui: &mut egui::Ui,
+    smart_folders: &SmartFolders,
+    selected: Option<&str>,
+) -> Option<SmartFolderAction> {
+    let mut action = None;
+
+    ui.heading("Smart folders");
+    ui.separator();
+
+    if smart_folders.folders().is_empty() {
+        ui.label(egui::RichText::new("Use 💾 to save a search").small().weak());
+    }
+
+    for folder in smart_folders.folders() {
+        let is_selected = selected == Some(folder.name.as_str());
+        let response = ui
+            .selectable_label(is_selected, format!("🗂 {}", folder.name))
+            .on_hover_text(format!("\"{}\" {}", folder.query, folder.describe_scope()));
+        if response.clicked() {
+            action = Some(SmartFolderAction::Open(folder.name.clone()));
+        }
+        response.context_menu(|ui| {
+            if ui.button("Edit…").clicked() {
+                action = Some(SmartFolderAction::Edit(folder.name.clone()));
+                ui.close_menu();
+            }
+            if ui.button("Export as JSON").clicked() {
+                action = Some(SmartFolderAction::Export(folder.name.clone()));
+                ui.close_menu();
+            }
+            if ui.button("Delete").clicked() {
+                action = Some(SmartFolderAction::Delete(folder.name.clone()));
+                ui.close_menu();
+            }
+        });
+    }
+
+    action
+}
+
+pub fn render_smart_folder_editor(
+    ctx: &egui::Context,
+    draft: &mut SmartFolderDraft,
+) -> Option<EditorResult> {
+    let mut result = None;
+
+    egui::Window::new("Smart folder")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            egui::Grid::new("smart_folder_editor")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Name:");
+                    ui.add(egui::TextEdit::singleline(&mut draft.name).desired_width(240.0));
+                    ui.end_row();
+
+                    ui.label("Query:");
+                    ui.add(egui::TextEdit::singleline(&mut draft.query).desired_width(240.0));
+                    ui.end_row();
+
+                    ui.label("Scope:");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut draft.scope_kind, ScopeKind::Everywhere, "Everywhere");
+                        ui.radio_value(&mut draft.scope_kind, ScopeKind::Directory, "Folder");
+                        ui.radio_value(&mut draft.scope_kind, ScopeKind::Tag, "Tag");
+                    });
+                    ui.end_row();
+
+                    match draft.scope_kind {
+                        ScopeKind::Everywhere => {}
+                        ScopeKind::Directory => {
+                            ui.label("Folder:");
+                            ui.add(egui::TextEdit::singleline(&mut draft.directory).desired_width(240.0));
+                            ui.end_row();
+                        }
+                        ScopeKind::Tag => {
+                            ui.label("Tag:");
+                            ui.add(egui::TextEdit::singleline(&mut draft.tag).desired_width(240.0));
+                            ui.end_row();
+                        }
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                let folder = draft.to_folder();
+                if ui
+                    .add_enabled(folder.is_some(), egui::Button::new("Save"))
+                    .clicked()
+                {
+                    result = folder.map(EditorResult::Save);
+                }
+                if ui.button("Cancel").clicked() {
+                    result = Some(EditorResult::Cancel);
+                }
+            });
+        });
+
+    if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+        result = Some(EditorResult::Cancel);
+    }
+
+    result
+}
@@ -0,0 +1,49 @@
+use eframe::egui;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::file_associations::FileAssociations;
+use crate::smart_folders::SmartFolder;
+use crate::tag_db::FileEntry;
+use crate::ui::file_list::{render_file_list, FileListAction, ListScroll};
+
+#[allow(clippy::too_many_arguments)]
+pub fn render_smart_view(
+    folder: Option<&SmartFolder>,
+    files: Vec<FileEntry>,
+    on_dir_click: &mut dyn FnMut(PathBuf),
+    selected_file_index: Option<usize>,
+    marked: &BTreeMap<PathBuf, FileEntry>,
+    scroll: &mut ListScroll,
+    file_associations: &FileAssociations,
+    ui: &mut egui::Ui,
+) -> Option<FileListAction> {
+    ui.vertical(|ui| {
+        match folder {
+            Some(folder) => {
+                ui.horizontal(|ui| {
+                    ui.heading(format!("🗂 {}", folder.name));
+                    ui.label(
+                        egui::RichText::new(format!("\"{}\" {}", folder.query, folder.describe_scope()))
+                            .weak(),
+                    );
+                });
+            }
+            None => {
+                ui.heading("Smart folders");
+            }
+        }
+        ui.separator();
+
+        if folder.is_none() {
+            ui.label("Pick a smart folder from the sidebar, or save the current search with 💾.");
+            return None;
+        }
+
+        ui.allocate_ui(ui.available_size(), |ui| {
+            render_file_list(ui, files, Some(on_dir_click), selected_file_index, marked, scroll, file_associations)
+        })
+        .inner
+    })
+    .inner
+}
@@ -0,0 +1,58 @@
+use eframe::egui;
+
+use crate::tabs::TabSet;
+use crate::ui::theme;
+
+pub enum TabBarAction {
+    Select(usize),
+    Close(usize),
+    New,
+}
+
+pub fn render_tab_bar(ui: &mut egui::Ui, tabs: &TabSet) -> Option<TabBarAction> {
+    let mut action = None;
+    let can_close = tabs.len() > 1;
+    let theme = theme::current(ui.ctx());
+
+    ui.horizontal(|ui| {
+        for (index, tab) in tabs.iter().enumerate() {
+            let is_active = index == tabs.active_index();
+            let title = tab.title();
+            let label_text = if title.chars().count() > 24 {
+                format!("{}...", title.chars().take(21).collect::<String>())
+            } else {
+                title.clone()
+            };
+
+            let response = ui
+                .selectable_label(is_active, label_text)
+                .on_hover_text(tab.folder_current_path.to_string_lossy());
+            if response.clicked() {
+                action = Some(TabBarAction::Select(index));
+            }
+            if response.middle_clicked() && can_close {
+                action = Some(TabBarAction::Close(index));
+            }
+
+            if can_close
+                && ui
+                    .add(
+                        egui::Label::new(
+                            egui::RichText::new("×").color(theme.text_secondary),
+                        )
+                        .sense(egui::Sense::click()),
+                    )
+                    .clicked()
+            {
+                action = Some(TabBarAction::Close(index));
+            }
+            ui.separator();
+        }
+
+        if ui.button("+").on_hover_text("New tab (Ctrl+T)").clicked() {
+            action = Some(TabBarAction::New);
+        }
+    });
+
+    action
+}
@@ -1,30 +1,44 @@
 use eframe::egui;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::file_associations::FileAssociations;
+use crate::file_kind::FileKind;
 use crate::tag_db::{TagDatabase, FileEntry};
-use crate::ui::file_list::render_file_list;
+use crate::ui::file_list::{render_file_list, FileListAction, ListScroll};
 use crate::ui::theme;
 
+#[allow(clippy::too_many_arguments)]
 pub fn render_tag_view(
     tag_db: Arc<TagDatabase>,
     files: Vec<FileEntry>,
     selected_tag: Option<String>,
     on_tag_select: &mut dyn FnMut(Option<String>),
+    selected_kind: Option<FileKind>,
+    on_kind_select: &mut dyn FnMut(FileKind),
     selected_file_index: Option<usize>,
+    marked: &BTreeMap<PathBuf, FileEntry>,
+    scroll: &mut ListScroll,
     file_associations: &FileAssociations,
+    tag_list_width: &mut f32,
     ui: &mut egui::Ui,
-) {
+) -> Option<FileListAction> {
     let tags_result = tag_db.get_all_tags();
     let tags = tags_result.unwrap_or_default();
+    let kinds = tag_db.get_kind_counts().unwrap_or_default();
+    let theme = theme::current(ui.ctx());
 
     ui.horizontal(|ui| {
-        egui::SidePanel::left("tag_list")
+        let tag_list = egui::SidePanel::left("tag_list")
             .resizable(true)
-            .default_width(200.0)
+            .default_width(*tag_list_width)
             .show_inside(ui, |ui| {
                 ui.vertical(|ui| {
-                    if ui.selectable_label(selected_tag.is_none(), "All Files").clicked() {
+                    if ui
+                        .selectable_label(selected_tag.is_none() && selected_kind.is_none(), "All Files")
+                        .clicked()
+                    {
                         on_tag_select(None);
                     }
                     ui.separator();
@@ -43,9 +57,33 @@ pub fn render_tag_view(
                                     egui::RichText::new(file_count.to_string())
                                         .size(10.0)
                                         .color(if is_selected {
-                                            theme::TEXT_PRIMARY
+                                            theme.text_primary
                                         } else {
-                                            theme::TEXT_SECONDARY
+                                            theme.text_secondary
+                                        })
+                                );
+                            });
+                        }
+
+                        if !kinds.is_empty() {
+                            ui.separator();
+                            ui.label(egui::RichText::new("Kinds").size(11.0).color(theme.text_secondary));
+                        }
+                        for (kind, file_count) in kinds {
+                            let is_selected = selected_kind == Some(kind);
+
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new(kind.icon()).color(theme.kind_color(kind)));
+                                if ui.selectable_label(is_selected, kind.label()).clicked() {
+                                    on_kind_select(kind);
+                                }
+                                ui.label(
+                                    egui::RichText::new(file_count.to_string())
+                                        .size(10.0)
+                                        .color(if is_selected {
+                                            theme.text_primary
+                                        } else {
+                                            theme.text_secondary
                                         })
                                 );
                             });
@@ -53,11 +91,15 @@ pub fn render_tag_view(
                     });
                 });
             });
+        *tag_list_width = tag_list.response.rect.width();
 
         ui.vertical(|ui| {
             ui.allocate_ui(ui.available_size(), |ui| {
-                render_file_list(ui, files, None, selected_file_index, file_associations);
-            });
-        });
-    });
+                render_file_list(ui, files, None, selected_file_index, marked, scroll, file_associations)
+            })
+            .inner
+        })
+        .inner
+    })
+    .inner
 }
@@ -1,15 +1,275 @@
+//! Colors for everything the app paints itself, together with the egui
+//! visuals underneath. Besides the built-in light and dark themes, every
+//! `.json` or `.toml` file in `~/.fms/themes` is loaded as a theme; it only
+//! needs the colors it changes, the rest come from the light or dark theme
+//! picked by its `dark` key.
+
 use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::file_kind::FileKind;
+
+/// The `settings.appearance.theme` value that follows the system.
+pub const FOLLOW_SYSTEM: &str = "system";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    pub name: String,
+    /// Builds on egui's dark visuals rather than its light ones.
+    pub dark: bool,
+    #[serde(with = "hex")]
+    pub background: egui::Color32,
+    #[serde(with = "hex")]
+    pub accent: egui::Color32,
+    #[serde(with = "hex")]
+    pub row_selected_bg: egui::Color32,
+    #[serde(with = "hex")]
+    pub row_hover_bg: egui::Color32,
+    #[serde(with = "hex")]
+    pub icon_directory: egui::Color32,
+    #[serde(with = "hex")]
+    pub icon_file: egui::Color32,
+    #[serde(with = "hex")]
+    pub icon_document: egui::Color32,
+    #[serde(with = "hex")]
+    pub icon_image: egui::Color32,
+    #[serde(with = "hex")]
+    pub icon_audio: egui::Color32,
+    #[serde(with = "hex")]
+    pub icon_video: egui::Color32,
+    #[serde(with = "hex")]
+    pub icon_archive: egui::Color32,
+    #[serde(with = "hex")]
+    pub icon_code: egui::Color32,
+    #[serde(with = "hex")]
+    pub icon_executable: egui::Color32,
+    #[serde(with = "hex")]
+    pub link_badge: egui::Color32,
+    #[serde(with = "hex")]
+    pub link_broken: egui::Color32,
+    #[serde(with = "hex")]
+    pub name_lossy: egui::Color32,
+    #[serde(with = "hex")]
+    pub text_primary: egui::Color32,
+    #[serde(with = "hex")]
+    pub text_secondary_selected: egui::Color32,
+    #[serde(with = "hex")]
+    pub text_secondary: egui::Color32,
+    #[serde(with = "hex")]
+    pub tree_current_stroke: egui::Color32,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            name: "Dark".to_string(),
+            dark: true,
+            background: egui::Color32::from_rgb(27, 27, 27),
+            accent: egui::Color32::from_rgb(0, 92, 128),
+            row_selected_bg: egui::Color32::from_rgb(50, 50, 50),
+            row_hover_bg: egui::Color32::from_rgba_unmultiplied(255, 255, 255, 2),
+            icon_directory: egui::Color32::from_rgb(0, 122, 255),
+            icon_file: egui::Color32::from_rgb(153, 153, 153),
+            icon_document: egui::Color32::from_rgb(220, 220, 220),
+            icon_image: egui::Color32::from_rgb(255, 159, 10),
+            icon_audio: egui::Color32::from_rgb(255, 55, 95),
+            icon_video: egui::Color32::from_rgb(191, 90, 242),
+            icon_archive: egui::Color32::from_rgb(172, 142, 104),
+            icon_code: egui::Color32::from_rgb(48, 209, 88),
+            icon_executable: egui::Color32::from_rgb(255, 214, 10),
+            link_badge: egui::Color32::from_rgb(90, 200, 250),
+            link_broken: egui::Color32::from_rgb(255, 69, 58),
+            name_lossy: egui::Color32::from_rgb(255, 159, 10),
+            text_primary: egui::Color32::WHITE,
+            text_secondary_selected: egui::Color32::from_rgb(200, 200, 200),
+            text_secondary: egui::Color32::from_rgb(102, 102, 102),
+            tree_current_stroke: egui::Color32::from_rgb(100, 150, 255),
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            name: "Light".to_string(),
+            dark: false,
+            background: egui::Color32::from_rgb(248, 248, 248),
+            accent: egui::Color32::from_rgb(144, 209, 255),
+            row_selected_bg: egui::Color32::from_rgb(220, 222, 228),
+            row_hover_bg: egui::Color32::from_rgba_unmultiplied(0, 0, 0, 8),
+            icon_directory: egui::Color32::from_rgb(0, 110, 230),
+            icon_file: egui::Color32::from_rgb(130, 130, 135),
+            icon_document: egui::Color32::from_rgb(80, 80, 85),
+            icon_image: egui::Color32::from_rgb(220, 120, 0),
+            icon_audio: egui::Color32::from_rgb(215, 30, 75),
+            icon_video: egui::Color32::from_rgb(150, 60, 200),
+            icon_archive: egui::Color32::from_rgb(130, 100, 65),
+            icon_code: egui::Color32::from_rgb(25, 150, 55),
+            icon_executable: egui::Color32::from_rgb(175, 135, 0),
+            link_badge: egui::Color32::from_rgb(0, 140, 200),
+            link_broken: egui::Color32::from_rgb(210, 35, 25),
+            name_lossy: egui::Color32::from_rgb(220, 120, 0),
+            text_primary: egui::Color32::from_rgb(20, 20, 20),
+            text_secondary_selected: egui::Color32::from_rgb(70, 70, 70),
+            text_secondary: egui::Color32::from_rgb(125, 125, 125),
+            tree_current_stroke: egui::Color32::from_rgb(40, 100, 230),
+        }
+    }
+
+    pub fn kind_color(&self, kind: FileKind) -> egui::Color32 {
+        match kind {
+            FileKind::Document => self.icon_document,
+            FileKind::Image => self.icon_image,
+            FileKind::Audio => self.icon_audio,
+            FileKind::Video => self.icon_video,
+            FileKind::Archive => self.icon_archive,
+            FileKind::Code => self.icon_code,
+            FileKind::Executable => self.icon_executable,
+            FileKind::Other => self.icon_file,
+        }
+    }
 
-pub const ROW_SELECTED_BG: egui::Color32 = egui::Color32::from_rgb(50, 50, 50);
-pub fn row_hover_bg() -> egui::Color32 {
-    egui::Color32::from_rgba_unmultiplied(255, 255, 255, 2)
+    pub fn visuals(&self) -> egui::Visuals {
+        let mut visuals = if self.dark {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+        visuals.panel_fill = self.background;
+        visuals.window_fill = self.background;
+        visuals.selection.bg_fill = self.accent;
+        visuals.hyperlink_color = self.icon_directory;
+        visuals
+    }
 }
 
-pub const ICON_DIRECTORY: egui::Color32 = egui::Color32::from_rgb(0, 122, 255);
-pub const ICON_FILE: egui::Color32 = egui::Color32::from_rgb(153, 153, 153);
+fn theme_id() -> egui::Id {
+    egui::Id::new("fms_theme")
+}
+
+/// Applies `theme` to the egui visuals and makes it the one `current` returns.
+pub fn apply(ctx: &egui::Context, theme: &Theme) {
+    ctx.set_visuals(theme.visuals());
+    ctx.data_mut(|data| data.insert_temp(theme_id(), Arc::new(theme.clone())));
+}
+
+/// The theme last passed to `apply`, or the dark theme before that.
+pub fn current(ctx: &egui::Context) -> Arc<Theme> {
+    ctx.data(|data| data.get_temp(theme_id()))
+        .unwrap_or_else(|| Arc::new(Theme::dark()))
+}
+
+/// The built-in themes followed by those loaded from `~/.fms/themes`.
+/// Files that fail to load are listed in `problems` for the settings window.
+pub struct Themes {
+    themes: Vec<Theme>,
+    problems: Vec<String>,
+}
 
-pub const TEXT_PRIMARY: egui::Color32 = egui::Color32::WHITE;
-pub const TEXT_SECONDARY_SELECTED: egui::Color32 = egui::Color32::from_rgb(200, 200, 200);
-pub const TEXT_SECONDARY: egui::Color32 = egui::Color32::from_rgb(102, 102, 102);
+impl Themes {
+    pub fn load(config_dir: &Path) -> Self {
+        let mut themes = vec![Theme::light(), Theme::dark()];
+        let mut problems = Vec::new();
 
-pub const TREE_CURRENT_STROKE: egui::Color32 = egui::Color32::from_rgb(100, 150, 255);
+        let themes_dir = config_dir.join("themes");
+        let mut paths: Vec<_> = match std::fs::read_dir(&themes_dir) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+            Err(_) => Vec::new(),
+        };
+        paths.sort();
+
+        for path in paths {
+            let extension = path.extension().and_then(|ext| ext.to_str());
+            if !matches!(extension, Some("json") | Some("toml")) {
+                continue;
+            }
+            match load_theme_file(&path) {
+                Ok(theme) => {
+                    themes.retain(|existing| !existing.name.eq_ignore_ascii_case(&theme.name));
+                    themes.push(theme);
+                }
+                Err(e) => problems.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+
+        Themes { themes, problems }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.themes.iter().map(|theme| theme.name.as_str())
+    }
+
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+
+    /// Looks up a `settings.appearance.theme` value. `FOLLOW_SYSTEM` and
+    /// unknown names pick the light or dark theme matching `system_dark`.
+    pub fn resolve(&self, choice: &str, system_dark: bool) -> &Theme {
+        self.themes
+            .iter()
+            .find(|theme| theme.name.eq_ignore_ascii_case(choice))
+            .or_else(|| {
+                let fallback = if system_dark { "Dark" } else { "Light" };
+                self.themes.iter().find(|theme| theme.name == fallback)
+            })
+            .unwrap_or(&self.themes[0])
+    }
+}
+
+/// Reads a theme file and fills in whatever it leaves out from the built-in
+/// theme of the same darkness. The name defaults to the file stem.
+fn load_theme_file(path: &Path) -> Result<Theme, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let value: serde_json::Value = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&content).map_err(|e| e.to_string())?
+    } else {
+        serde_json::from_str(&content).map_err(|e| e.to_string())?
+    };
+    let serde_json::Value::Object(overrides) = value else {
+        return Err("expected a table of colors".to_string());
+    };
+
+    let dark = overrides.get("dark").and_then(serde_json::Value::as_bool).unwrap_or(true);
+    let mut base = if dark { Theme::dark() } else { Theme::light() };
+    if let Some(stem) = path.file_stem() {
+        base.name = stem.to_string_lossy().to_string();
+    }
+    let mut merged = serde_json::to_value(base).map_err(|e| e.to_string())?;
+    if let Some(fields) = merged.as_object_mut() {
+        fields.extend(overrides);
+    }
+    serde_json::from_value(merged).map_err(|e| e.to_string())
+}
+
+/// Colors as `#rrggbb`, or `#rrggbbaa` when not opaque.
+mod hex {
+    use eframe::egui::Color32;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let text = if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        };
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse(&text).ok_or_else(|| serde::de::Error::custom(format!("invalid color {:?}", text)))
+    }
+
+    fn parse(text: &str) -> Option<Color32> {
+        let digits = text.strip_prefix('#')?;
+        if !matches!(digits.len(), 6 | 8) || !digits.is_ascii() {
+            return None;
+        }
+        let channel = |index: usize| u8::from_str_radix(&digits[index..index + 2], 16).ok();
+        let alpha = if digits.len() == 8 { channel(6)? } else { 255 };
+        Some(Color32::from_rgba_unmultiplied(channel(0)?, channel(2)?, channel(4)?, alpha))
+    }
+}
@@ -0,0 +1,104 @@
+//! Zip and tar archives, read-only; see `crate::archive`.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::archive::{self, ArchiveEntry};
+use crate::notifications;
+use crate::tag_db::FileEntry;
+use crate::vfs::{self, Vfs};
+
+/// How many archives keep their listing cached.
+const CACHED_LISTINGS: usize = 4;
+
+struct Listing {
+    archive: PathBuf,
+    modified: Option<SystemTime>,
+    entries: Arc<Result<Vec<ArchiveEntry>, String>>,
+}
+
+/// Keeps the listings of the last few browsed archives, re-read when the
+/// archive's modification time changes. Failures are kept too, so a broken
+/// archive is reported once rather than on every listing.
+#[derive(Default)]
+pub struct ArchiveVfs {
+    /// Least recently used first.
+    listings: Mutex<Vec<Listing>>,
+}
+
+impl ArchiveVfs {
+    fn entries(&self, archive: &Path) -> io::Result<Arc<Result<Vec<ArchiveEntry>, String>>> {
+        let modified = std::fs::metadata(archive)?.modified().ok();
+        let mut listings = self.listings.lock().unwrap();
+        let cached = listings.iter().position(|listing| listing.archive == archive).map(|index| listings.remove(index));
+        let listing = match cached {
+            Some(listing) if listing.modified == modified => listing,
+            _ => {
+                let entries = Arc::new(archive::read_entries(archive).map_err(|e| e.to_string()));
+                if let Err(e) = entries.as_ref() {
+                    notifications::error!(format!("Could not read archive: {}", e), Some(archive));
+                }
+                Listing {
+                    archive: archive.to_path_buf(),
+                    modified,
+                    entries,
+                }
+            }
+        };
+        let entries = listing.entries.clone();
+        if listings.len() >= CACHED_LISTINGS {
+            listings.remove(0);
+        }
+        listings.push(listing);
+        Ok(entries)
+    }
+}
+
+fn split(path: &Path) -> io::Result<(PathBuf, PathBuf)> {
+    archive::split(path).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not inside an archive"))
+}
+
+impl Vfs for ArchiveVfs {
+    fn list(&self, dir: &Path) -> io::Result<Vec<FileEntry>> {
+        let (archive, inner) = split(dir)?;
+        match self.entries(&archive)?.as_ref() {
+            Ok(entries) => Ok(entries
+                .iter()
+                .filter(|entry| entry.path.parent() == Some(inner.as_path()))
+                .map(|entry| archive::to_file_entry(&archive, entry))
+                .collect()),
+            Err(e) => Err(io::Error::other(e.clone())),
+        }
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<FileEntry> {
+        let (archive, inner) = split(path)?;
+        match self.entries(&archive)?.as_ref() {
+            Ok(entries) => entries
+                .iter()
+                .find(|entry| entry.path == inner)
+                .map(|entry| archive::to_file_entry(&archive, entry))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in the archive")),
+            Err(e) => Err(io::Error::other(e.clone())),
+        }
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let (archive, inner) = split(path)?;
+        archive::read_entry(&archive, &inner)
+    }
+
+    fn write(&self, _path: &Path, _contents: &[u8]) -> io::Result<()> {
+        Err(vfs::read_only())
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(vfs::read_only())
+    }
+
+    fn remove(&self, _path: &Path) -> io::Result<()> {
+        Err(vfs::read_only())
+    }
+}
@@ -0,0 +1,249 @@
+//! The local disk, through `std::fs`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::SystemTime;
+
+use sysinfo::{Groups, Users};
+
+use crate::file_kind;
+use crate::notifications;
+use crate::tag_db::{Classified, FileEntry, FileMetadata, FileType, LinkInfo};
+use crate::vfs::Vfs;
+
+/// BSD file flags (`st_flags`), which carry the hidden flag; only macOS
+/// exposes them.
+#[cfg(target_os = "macos")]
+fn file_flags(metadata: &std::fs::Metadata) -> u32 {
+    std::os::macos::fs::MetadataExt::st_flags(metadata)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn file_flags(_metadata: &std::fs::Metadata) -> u32 {
+    0
+}
+
+/// uid and gid to name lookups, loaded once from the system user database.
+struct OwnerNames {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl OwnerNames {
+    fn load() -> Self {
+        OwnerNames {
+            users: Users::new_with_refreshed_list()
+                .list()
+                .iter()
+                .map(|user| (**user.id(), user.name().to_string()))
+                .collect(),
+            groups: Groups::new_with_refreshed_list()
+                .list()
+                .iter()
+                .map(|group| (**group.id(), group.name().to_string()))
+                .collect(),
+        }
+    }
+}
+
+/// Entries are read with owner and group names and a detected MIME type
+/// and kind, the same way the indexer stores them.
+pub struct LocalVfs {
+    owner_names: OwnerNames,
+}
+
+impl LocalVfs {
+    pub fn new() -> Self {
+        LocalVfs {
+            owner_names: OwnerNames::load(),
+        }
+    }
+
+    /// Reads `path` without following it. Symlinks are described with their
+    /// target; size and times come from the target unless the link is broken.
+    /// Also returns the metadata of what `path` resolves to (`None` for a
+    /// broken link), which decides whether the indexer descends into it.
+    pub fn read_entry(&self, path: &Path) -> io::Result<(FileEntry, Option<fs::Metadata>)> {
+        self.read_entry_reusing(path, None)
+    }
+
+    /// Like `read_entry`, but takes the MIME type and kind from `previous`
+    /// when the file's size and modification time still match it, instead
+    /// of reading its first bytes again. Only regular files are ever read:
+    /// opening a FIFO or a device could block.
+    pub fn read_entry_reusing(
+        &self,
+        path: &Path,
+        previous: Option<&Classified>,
+    ) -> io::Result<(FileEntry, Option<fs::Metadata>)> {
+        self.read_entry_with(path, previous, true)
+    }
+
+    /// Lists `dir` like `list`, but classifies files by name and mode only,
+    /// without reading any of them.
+    pub fn list_by_name(&self, dir: &Path) -> io::Result<Vec<FileEntry>> {
+        self.list_with(dir, false)
+    }
+
+    fn list_with(&self, dir: &Path, sniff: bool) -> io::Result<Vec<FileEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    notifications::warning!(format!("Could not read directory entry: {}", e), Some(dir));
+                    continue;
+                }
+            };
+            match self.read_entry_with(&path, None, sniff) {
+                Ok((entry, _)) => entries.push(entry),
+                Err(e) => notifications::warning!(format!("Could not read: {}", e), Some(&path)),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn read_entry_with(
+        &self,
+        path: &Path,
+        previous: Option<&Classified>,
+        sniff: bool,
+    ) -> io::Result<(FileEntry, Option<fs::Metadata>)> {
+        let link_metadata = fs::symlink_metadata(path)?;
+        let (file_type, link, resolved) = if link_metadata.file_type().is_symlink() {
+            let resolved = fs::metadata(path).ok();
+            let link = LinkInfo {
+                target: fs::read_link(path)?,
+                target_type: resolved.as_ref().map(|metadata| {
+                    if metadata.is_dir() {
+                        FileType::Directory
+                    } else {
+                        FileType::File
+                    }
+                }),
+            };
+            (FileType::Symlink, Some(link), resolved)
+        } else if link_metadata.is_dir() {
+            (FileType::Directory, None, Some(link_metadata.clone()))
+        } else {
+            (FileType::File, None, Some(link_metadata.clone()))
+        };
+        let metadata = resolved.as_ref().unwrap_or(&link_metadata);
+
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let size = metadata.len();
+        let modified = match metadata.modified() {
+            Ok(time) => match time.duration_since(SystemTime::UNIX_EPOCH) {
+                Ok(duration) => duration.as_secs() as i64,
+                Err(e) => {
+                    notifications::warning!(format!("Modification time before 1970: {}", e), Some(path));
+                    0
+                }
+            },
+            Err(e) => {
+                notifications::warning!(format!("Could not read modification time: {}", e), Some(path));
+                0
+            }
+        };
+
+        let parent = path.parent().map(|p| p.to_path_buf());
+
+        let mut file_metadata = FileMetadata {
+            mode: metadata.mode(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            owner: self.owner_names.users.get(&metadata.uid()).cloned(),
+            group: self.owner_names.groups.get(&metadata.gid()).cloned(),
+            created: metadata
+                .created()
+                .ok()
+                .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs() as i64),
+            accessed: metadata.atime(),
+            device: metadata.dev(),
+            inode: metadata.ino(),
+            nlink: metadata.nlink(),
+            flags: file_flags(&link_metadata),
+            extension: None,
+            mime: None,
+            kind: None,
+        };
+        if !metadata.is_dir() {
+            file_metadata.extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        }
+        if resolved.as_ref().is_some_and(fs::Metadata::is_file) {
+            match previous.filter(|previous| previous.size == size && previous.modified == modified) {
+                Some(previous) => {
+                    file_metadata.mime = previous.mime.clone();
+                    file_metadata.kind = previous.kind;
+                }
+                None => {
+                    let extension = file_metadata.extension.as_deref();
+                    let (mime, kind) = if sniff {
+                        file_kind::classify(path, extension, file_metadata.mode)
+                    } else {
+                        file_kind::classify_by_name(extension, file_metadata.mode)
+                    };
+                    file_metadata.mime = mime;
+                    file_metadata.kind = Some(kind);
+                }
+            }
+        }
+
+        let entry = FileEntry {
+            path: path.to_path_buf(),
+            name,
+            file_type,
+            size,
+            modified,
+            parent,
+            ignored: false,
+            link,
+            metadata: file_metadata,
+        };
+
+        Ok((entry, resolved))
+    }
+}
+
+impl Vfs for LocalVfs {
+    /// Unreadable entries are reported and left out.
+    fn list(&self, dir: &Path) -> io::Result<Vec<FileEntry>> {
+        self.list_with(dir, true)
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<FileEntry> {
+        self.read_entry(path).map(|(entry, _)| entry)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        if fs::symlink_metadata(path)?.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+}
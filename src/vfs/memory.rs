@@ -0,0 +1,171 @@
+//! A filesystem held in memory, for exercising code written against `Vfs`
+//! without touching the disk. Paths are absolute; `/` always exists.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::tag_db::{FileEntry, FileMetadata, FileType};
+use crate::vfs::Vfs;
+
+const DIR_MODE: u32 = 0o040755;
+const FILE_MODE: u32 = 0o100644;
+
+struct Node {
+    /// `None` for folders.
+    contents: Option<Vec<u8>>,
+    modified: i64,
+}
+
+#[derive(Default)]
+pub struct MemoryVfs {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} does not exist", path.display()))
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+impl MemoryVfs {
+    pub fn new() -> Self {
+        MemoryVfs::default()
+    }
+
+    /// Creates `path` and any missing folders above it.
+    pub fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        Self::create_dirs(&mut nodes, path)
+    }
+
+    fn create_dirs(nodes: &mut BTreeMap<PathBuf, Node>, path: &Path) -> io::Result<()> {
+        for dir in path.ancestors().filter(|dir| dir.parent().is_some()) {
+            match nodes.get(dir) {
+                Some(Node { contents: Some(_), .. }) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotADirectory,
+                        format!("{} is a file", dir.display()),
+                    ))
+                }
+                Some(_) => {}
+                None => {
+                    nodes.insert(dir.to_path_buf(), Node {
+                        contents: None,
+                        modified: now(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_dir(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> bool {
+        path.parent().is_none() || nodes.get(path).is_some_and(|node| node.contents.is_none())
+    }
+
+    fn entry(path: &Path, node: &Node) -> FileEntry {
+        let is_dir = node.contents.is_none();
+        FileEntry {
+            path: path.to_path_buf(),
+            name: path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+            file_type: if is_dir { FileType::Directory } else { FileType::File },
+            size: node.contents.as_ref().map_or(0, |contents| contents.len() as u64),
+            modified: node.modified,
+            parent: path.parent().map(Path::to_path_buf),
+            ignored: false,
+            link: None,
+            metadata: FileMetadata {
+                mode: if is_dir { DIR_MODE } else { FILE_MODE },
+                nlink: 1,
+                extension: (!is_dir)
+                    .then(|| path.extension().map(|ext| ext.to_string_lossy().to_lowercase()))
+                    .flatten(),
+                ..FileMetadata::default()
+            },
+        }
+    }
+}
+
+impl Vfs for MemoryVfs {
+    fn list(&self, dir: &Path) -> io::Result<Vec<FileEntry>> {
+        let nodes = self.nodes.lock().unwrap();
+        if !Self::is_dir(&nodes, dir) {
+            return Err(not_found(dir));
+        }
+        Ok(nodes
+            .iter()
+            .filter(|(path, _)| path.parent() == Some(dir))
+            .map(|(path, node)| Self::entry(path, node))
+            .collect())
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<FileEntry> {
+        let nodes = self.nodes.lock().unwrap();
+        nodes
+            .get(path)
+            .map(|node| Self::entry(path, node))
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let nodes = self.nodes.lock().unwrap();
+        match nodes.get(path) {
+            Some(Node { contents: Some(contents), .. }) => Ok(contents.clone()),
+            Some(_) => Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a folder", path.display()))),
+            None => Err(not_found(path)),
+        }
+    }
+
+    /// Missing folders above `path` are created.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if Self::is_dir(&nodes, path) {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a folder", path.display())));
+        }
+        if let Some(parent) = path.parent() {
+            Self::create_dirs(&mut nodes, parent)?;
+        }
+        nodes.insert(path.to_path_buf(), Node {
+            contents: Some(contents.to_vec()),
+            modified: now(),
+        });
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if !nodes.contains_key(from) {
+            return Err(not_found(from));
+        }
+        if !Self::is_dir(&nodes, to.parent().unwrap_or(Path::new("/"))) {
+            return Err(not_found(to));
+        }
+        if Self::is_dir(&nodes, to) {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a folder", to.display())));
+        }
+        let moved: Vec<PathBuf> = nodes.keys().filter(|path| path.starts_with(from)).cloned().collect();
+        for path in moved {
+            if let Some(node) = nodes.remove(&path) {
+                let moved_to = match path.strip_prefix(from) {
+                    Ok(relative) if !relative.as_os_str().is_empty() => to.join(relative),
+                    _ => to.to_path_buf(),
+                };
+                nodes.insert(moved_to, node);
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if !nodes.contains_key(path) {
+            return Err(not_found(path));
+        }
+        nodes.retain(|node_path, _| !node_path.starts_with(path));
+        Ok(())
+    }
+}
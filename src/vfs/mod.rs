@@ -0,0 +1,133 @@
+//! Filesystem backends behind one interface, so views can list and change
+//! folders that are not plain directories on a local disk. Every backend
+//! addresses entries by the same paths the UI shows: a file inside an
+//! archive is `/home/me/photos.zip/2024/beach.jpg`, a trashed file is its
+//! path under the trash folder.
+
+pub mod archive;
+pub mod local;
+#[cfg(test)]
+pub mod memory;
+pub mod tags;
+pub mod trash;
+
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::tag_db::{FileEntry, TagDatabase};
+
+use self::archive::ArchiveVfs;
+use self::local::LocalVfs;
+use self::tags::TagVfs;
+use self::trash::TrashVfs;
+
+/// How long a listing from a backend other than the index is reused before
+/// it is read again.
+const LISTING_TTL: Duration = Duration::from_secs(2);
+
+pub trait Vfs: Send + Sync {
+    /// The entries directly inside `dir`.
+    fn list(&self, dir: &Path) -> io::Result<Vec<FileEntry>>;
+    fn stat(&self, path: &Path) -> io::Result<FileEntry>;
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    /// The contents as a stream, for files too large to read at once.
+    /// Backends that cannot stream read the whole file.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(io::Cursor::new(self.read(path)?)))
+    }
+    /// Creates or replaces the file at `path`.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    /// Replaces `to` if it is a file, like `std::fs::rename`.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Removes a file, or a folder with everything in it.
+    fn remove(&self, path: &Path) -> io::Result<()>;
+}
+
+/// The error read-only backends return from `write`, `rename` and `remove`.
+pub fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::ReadOnlyFilesystem, "this location is read-only")
+}
+
+/// What a folder view shows, and which backend serves it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    /// A folder on a local disk.
+    Local(PathBuf),
+    /// A folder inside an archive, or the archive's top level.
+    Archive(PathBuf),
+    /// A folder inside the trash.
+    Trash(PathBuf),
+    /// Every file carrying a tag.
+    Tag(String),
+}
+
+impl Location {
+    /// The location a folder path belongs to.
+    pub fn of_path(path: &Path) -> Location {
+        if crate::archive::split(path).is_some() {
+            Location::Archive(path.to_path_buf())
+        } else if trash::contains(path) {
+            Location::Trash(path.to_path_buf())
+        } else {
+            Location::Local(path.to_path_buf())
+        }
+    }
+}
+
+/// One instance of every backend, as used by the app.
+pub struct Filesystems {
+    pub local: Arc<LocalVfs>,
+    pub archives: Arc<ArchiveVfs>,
+    pub trash: Arc<TrashVfs>,
+    pub tags: Arc<TagVfs>,
+    last_listing: Mutex<Option<(Location, Instant, Vec<FileEntry>)>>,
+}
+
+impl Filesystems {
+    pub fn new(tag_db: Arc<TagDatabase>) -> Self {
+        let local = Arc::new(LocalVfs::new());
+        Filesystems {
+            archives: Arc::new(ArchiveVfs::default()),
+            trash: Arc::new(TrashVfs::new(local.clone())),
+            tags: Arc::new(TagVfs::new(tag_db, local.clone())),
+            local,
+            last_listing: Mutex::new(None),
+        }
+    }
+
+    /// The backend that owns `path`, shareable with background threads.
+    pub fn for_path(&self, path: &Path) -> Arc<dyn Vfs> {
+        match Location::of_path(path) {
+            Location::Archive(_) => self.archives.clone(),
+            Location::Trash(_) => self.trash.clone(),
+            Location::Local(_) | Location::Tag(_) => self.local.clone(),
+        }
+    }
+
+    /// The entries at `location`. Listings are reused for a moment, so
+    /// calling this every frame does not re-read the backend every frame.
+    pub fn list(&self, location: &Location) -> io::Result<Vec<FileEntry>> {
+        let mut last_listing = self.last_listing.lock().unwrap();
+        if let Some((listed, loaded, entries)) = last_listing.as_ref() {
+            if listed == location && loaded.elapsed() < LISTING_TTL {
+                return Ok(entries.clone());
+            }
+        }
+
+        let entries = match location {
+            Location::Local(dir) => self.local.list(dir),
+            Location::Archive(dir) => self.archives.list(dir),
+            Location::Trash(dir) => self.trash.list(dir),
+            Location::Tag(tag) => self.tags.list(Path::new(tag)),
+        }?;
+        *last_listing = Some((location.clone(), Instant::now(), entries.clone()));
+        Ok(entries)
+    }
+
+    /// Drops the reused listing after a change made through a backend.
+    pub fn invalidate(&self) {
+        *self.last_listing.lock().unwrap() = None;
+    }
+}
@@ -0,0 +1,51 @@
+//! Tags as folders: listing a tag gives every indexed file carrying it.
+//! The files themselves are ordinary local files, so everything but `list`
+//! goes to the local disk.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::tag_db::{FileEntry, TagDatabase};
+use crate::vfs::local::LocalVfs;
+use crate::vfs::Vfs;
+
+pub struct TagVfs {
+    tag_db: Arc<TagDatabase>,
+    local: Arc<LocalVfs>,
+}
+
+impl TagVfs {
+    pub fn new(tag_db: Arc<TagDatabase>, local: Arc<LocalVfs>) -> Self {
+        TagVfs { tag_db, local }
+    }
+}
+
+impl Vfs for TagVfs {
+    /// `dir` is the tag name.
+    fn list(&self, dir: &Path) -> io::Result<Vec<FileEntry>> {
+        self.tag_db
+            .get_files_by_tag(&dir.to_string_lossy())
+            .map_err(io::Error::other)
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<FileEntry> {
+        self.local.stat(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.local.read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.local.write(path, contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.local.rename(from, to)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.local.remove(path)
+    }
+}
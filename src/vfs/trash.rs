@@ -0,0 +1,90 @@
+//! The user's trash. On Linux this is the freedesktop.org home trash, where
+//! each trashed item has a `.trashinfo` file recording where it came from;
+//! on macOS it is `~/.Trash`, which keeps no such record.
+
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::settings;
+use crate::tag_db::FileEntry;
+use crate::vfs::local::LocalVfs;
+use crate::vfs::Vfs;
+
+/// The folder holding trashed files and folders.
+#[cfg(target_os = "macos")]
+pub fn files_dir() -> Option<PathBuf> {
+    settings::home_dir().map(|home| home.join(".Trash"))
+}
+
+/// The folder holding trashed files and folders.
+#[cfg(not(target_os = "macos"))]
+pub fn files_dir() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| settings::home_dir().map(|home| home.join(".local").join("share")))?;
+    Some(data_dir.join("Trash").join("files"))
+}
+
+/// True for the trash folder and everything in it.
+pub fn contains(path: &Path) -> bool {
+    files_dir().is_some_and(|dir| path.starts_with(dir))
+}
+
+/// The `.trashinfo` file of an item directly inside the trash folder.
+fn info_path(path: &Path) -> Option<PathBuf> {
+    let files_dir = files_dir()?;
+    if path.parent() != Some(files_dir.as_path()) {
+        return None;
+    }
+    let mut name = path.file_name()?.to_os_string();
+    name.push(".trashinfo");
+    Some(files_dir.parent()?.join("info").join(name))
+}
+
+pub struct TrashVfs {
+    local: Arc<LocalVfs>,
+}
+
+impl TrashVfs {
+    pub fn new(local: Arc<LocalVfs>) -> Self {
+        TrashVfs { local }
+    }
+}
+
+impl Vfs for TrashVfs {
+    /// Listed on the UI thread, so files are not read to tell their kind.
+    fn list(&self, dir: &Path) -> io::Result<Vec<FileEntry>> {
+        self.local.list_by_name(dir)
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<FileEntry> {
+        self.local.stat(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.local.read(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        self.local.open(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.local.write(path, contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.local.rename(from, to)
+    }
+
+    /// Deletes permanently, along with the item's trash info.
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.local.remove(path)?;
+        match info_path(path).map(std::fs::remove_file) {
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}